@@ -0,0 +1,313 @@
+//! The ball and what it bounces off.
+//!
+//! The ball is swept along its whole move each step instead of being
+//! checked where it ends up, so however fast it goes it can't jump over a
+//! paddle. It bounces off the face, the top and bottom edges or the
+//! rounded corners of a paddle, whichever it touches first.
+
+/// Sideways speed the ball gains on each paddle hit, in pixels per second.
+pub const BALL_ACC: f32 = 12.0;
+/// The ball never goes faster than this, in pixels per second.
+pub const MAX_BALL_SPEED: f32 = 1500.0;
+/// Up or down speed the ball gets per pixel it hits away from the middle
+/// of the paddle.
+pub const SPIN: f32 = 3.0;
+/// Bounces worked out in one step at most, the rest of the step is lost.
+const MAX_BOUNCES: usize = 4;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Rect {
+    pub x: f32,
+    pub y: f32,
+    pub w: f32,
+    pub h: f32,
+}
+
+impl Rect {
+    pub fn new(x: f32, y: f32, w: f32, h: f32) -> Rect {
+        Rect { x, y, w, h }
+    }
+
+    fn closest_point(&self, (x, y): (f32, f32)) -> (f32, f32) {
+        (x.max(self.x).min(self.x + self.w), y.max(self.y).min(self.y + self.h))
+    }
+}
+
+/// Where a moving circle first touches something.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Hit {
+    /// Share of the move made before touching, from 0 to 1.
+    pub time: f32,
+    /// Unit normal of the surface touched, pointing toward the circle.
+    pub normal: (f32, f32),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Ball {
+    pub x: f32,
+    pub y: f32,
+    /// Where the ball was before the last step, drawn in between.
+    pub prev_x: f32,
+    pub prev_y: f32,
+    /// Pixels per second.
+    pub vel_x: f32,
+    pub vel_y: f32,
+    pub radius: f32,
+}
+
+impl Ball {
+    /// A ball at rest at `(x, y)`.
+    pub fn new(x: f32, y: f32) -> Ball {
+        Ball {
+            x,
+            y,
+            prev_x: x,
+            prev_y: y,
+            vel_x: 0.0,
+            vel_y: 0.0,
+            radius: 10.0,
+        }
+    }
+
+    /// Puts the ball at `(x, y)` without it being drawn sliding there.
+    pub fn place(&mut self, x: f32, y: f32) {
+        self.x = x;
+        self.y = y;
+        self.prev_x = x;
+        self.prev_y = y;
+    }
+
+    pub fn speed(&self) -> f32 {
+        self.vel_x.hypot(self.vel_y)
+    }
+
+    /// Moves the ball on by `dt` seconds on a court `court_h` high,
+    /// bouncing it off the top and bottom and off `paddles`. Returns the
+    /// index of each paddle it hit.
+    pub fn update(&mut self, dt: f32, court_h: f32, paddles: &[Rect]) -> Vec<usize> {
+        self.prev_x = self.x;
+        self.prev_y = self.y;
+        let mut hits = Vec::new();
+
+        // a paddle moving into the ball pushes it out first
+        for (i, paddle) in paddles.iter().enumerate() {
+            if let Some(normal) = self.push_out(paddle) {
+                if self.vel_x * normal.0 + self.vel_y * normal.1 < 0.0 {
+                    self.bounce_off_paddle(normal, paddle);
+                    hits.push(i);
+                }
+            }
+        }
+        self.y = self.y.max(self.radius).min(court_h - self.radius);
+
+        let mut time_left = dt;
+        for _ in 0..MAX_BOUNCES {
+            let delta = (self.vel_x * time_left, self.vel_y * time_left);
+            let mut first: Option<(Hit, Option<usize>)> = self
+                .sweep_walls(delta, court_h)
+                .map(|hit| (hit, None));
+            for (i, paddle) in paddles.iter().enumerate() {
+                if let Some(hit) = sweep(self.position(), delta, self.radius, paddle) {
+                    if first.is_none_or(|(earliest, _)| hit.time < earliest.time) {
+                        first = Some((hit, Some(i)));
+                    }
+                }
+            }
+
+            let (hit, paddle) = match first {
+                Some(first) => first,
+                None => {
+                    self.x += delta.0;
+                    self.y += delta.1;
+                    break;
+                }
+            };
+            self.x += delta.0 * hit.time;
+            self.y += delta.1 * hit.time;
+            match paddle {
+                Some(i) => {
+                    self.bounce_off_paddle(hit.normal, &paddles[i]);
+                    hits.push(i);
+                }
+                None => {
+                    let (vel_x, vel_y) = reflect((self.vel_x, self.vel_y), hit.normal);
+                    self.vel_x = vel_x;
+                    self.vel_y = vel_y;
+                }
+            }
+            time_left *= 1.0 - hit.time;
+        }
+        hits
+    }
+
+    fn position(&self) -> (f32, f32) {
+        (self.x, self.y)
+    }
+
+    /// Top or bottom wall the ball reaches on a move of `delta`.
+    fn sweep_walls(&self, delta: (f32, f32), court_h: f32) -> Option<Hit> {
+        let (wall, normal) = if delta.1 < 0.0 {
+            (self.radius, (0.0, 1.0))
+        } else if delta.1 > 0.0 {
+            (court_h - self.radius, (0.0, -1.0))
+        } else {
+            return None;
+        };
+        let time = (wall - self.y) / delta.1;
+        if (0.0..=1.0).contains(&time) {
+            Some(Hit { time, normal })
+        } else {
+            None
+        }
+    }
+
+    /// Bounces back off `paddle`, faster and with spin the further from its
+    /// middle it hits, up to `MAX_BALL_SPEED`.
+    fn bounce_off_paddle(&mut self, normal: (f32, f32), paddle: &Rect) {
+        let (vel_x, vel_y) = reflect((self.vel_x, self.vel_y), normal);
+        self.vel_x = vel_x + BALL_ACC * vel_x.signum();
+        self.vel_y = vel_y + (self.y - (paddle.y + paddle.h / 2.0)) * SPIN;
+
+        let speed = self.speed();
+        if speed > MAX_BALL_SPEED {
+            self.vel_x *= MAX_BALL_SPEED / speed;
+            self.vel_y *= MAX_BALL_SPEED / speed;
+        }
+    }
+
+    /// Moves the ball out of `rect` if it overlaps it, returning the normal
+    /// it was pushed along.
+    fn push_out(&mut self, rect: &Rect) -> Option<(f32, f32)> {
+        let (near_x, near_y) = rect.closest_point(self.position());
+        let (dx, dy) = (self.x - near_x, self.y - near_y);
+        let distance = dx.hypot(dy);
+        if distance >= self.radius {
+            return None;
+        }
+
+        if distance > 0.0 {
+            let normal = (dx / distance, dy / distance);
+            self.x = near_x + normal.0 * self.radius;
+            self.y = near_y + normal.1 * self.radius;
+            return Some(normal);
+        }
+        // the center is inside, out through the nearest side
+        let sides = [
+            (self.x - rect.x, (-1.0, 0.0)),
+            (rect.x + rect.w - self.x, (1.0, 0.0)),
+            (self.y - rect.y, (0.0, -1.0)),
+            (rect.y + rect.h - self.y, (0.0, 1.0)),
+        ];
+        let &(depth, normal) = sides
+            .iter()
+            .min_by(|a, b| a.0.partial_cmp(&b.0).unwrap())
+            .unwrap();
+        self.x += normal.0 * (depth + self.radius);
+        self.y += normal.1 * (depth + self.radius);
+        Some(normal)
+    }
+}
+
+/// First touch of a circle of `radius` moving from `from` by `delta` with
+/// `rect`. A circle already touching it isn't hit again.
+///
+/// The circle touches the rectangle when its center enters the rectangle
+/// grown by `radius` all round with rounded corners, made here of a wide
+/// and a tall box and the four corner circles.
+pub fn sweep(from: (f32, f32), delta: (f32, f32), radius: f32, rect: &Rect) -> Option<Hit> {
+    let r = radius;
+    let wide = Rect::new(rect.x - r, rect.y, rect.w + 2.0 * r, rect.h);
+    let tall = Rect::new(rect.x, rect.y - r, rect.w, rect.h + 2.0 * r);
+    let corners = [
+        (rect.x, rect.y),
+        (rect.x + rect.w, rect.y),
+        (rect.x, rect.y + rect.h),
+        (rect.x + rect.w, rect.y + rect.h),
+    ];
+
+    let mut first = sweep_box(from, delta, &wide);
+    let mut consider = |hit: Option<Hit>| {
+        if let Some(hit) = hit {
+            if first.is_none_or(|earliest| hit.time < earliest.time) {
+                first = Some(hit);
+            }
+        }
+    };
+    consider(sweep_box(from, delta, &tall));
+    for &corner in &corners {
+        consider(sweep_circle(from, delta, corner, r));
+    }
+    first
+}
+
+/// Where a point moving from `from` by `delta` enters `rect`.
+fn sweep_box(from: (f32, f32), delta: (f32, f32), rect: &Rect) -> Option<Hit> {
+    let axes = [
+        (from.0, delta.0, rect.x, rect.x + rect.w, (1.0, 0.0)),
+        (from.1, delta.1, rect.y, rect.y + rect.h, (0.0, 1.0)),
+    ];
+    let mut enter = f32::NEG_INFINITY;
+    let mut exit = f32::INFINITY;
+    let mut normal = (0.0, 0.0);
+    for &(start, step, low, high, axis) in &axes {
+        if step == 0.0 {
+            if start < low || start > high {
+                return None;
+            }
+            continue;
+        }
+        let (near, far, side) = if step > 0.0 {
+            ((low - start) / step, (high - start) / step, -1.0)
+        } else {
+            ((high - start) / step, (low - start) / step, 1.0)
+        };
+        if near > enter {
+            enter = near;
+            normal = (axis.0 * side, axis.1 * side);
+        }
+        exit = exit.min(far);
+    }
+    // starting inside is no hit
+    if enter > exit || !(0.0..=1.0).contains(&enter) {
+        return None;
+    }
+    Some(Hit {
+        time: enter,
+        normal,
+    })
+}
+
+/// Where a point moving from `from` by `delta` reaches the circle around
+/// `center`.
+fn sweep_circle(from: (f32, f32), delta: (f32, f32), center: (f32, f32), radius: f32) -> Option<Hit> {
+    let (fx, fy) = (from.0 - center.0, from.1 - center.1);
+    let a = delta.0 * delta.0 + delta.1 * delta.1;
+    let b = 2.0 * (fx * delta.0 + fy * delta.1);
+    let c = fx * fx + fy * fy - radius * radius;
+    // already inside, or not moving
+    if c <= 0.0 || a == 0.0 {
+        return None;
+    }
+    let discriminant = b * b - 4.0 * a * c;
+    if discriminant < 0.0 {
+        return None;
+    }
+    let time = (-b - discriminant.sqrt()) / (2.0 * a);
+    if !(0.0..=1.0).contains(&time) {
+        return None;
+    }
+    let (x, y) = (fx + delta.0 * time, fy + delta.1 * time);
+    Some(Hit {
+        time,
+        normal: (x / radius, y / radius),
+    })
+}
+
+/// `vel` bounced off a surface with unit `normal`, when heading into it.
+pub fn reflect(vel: (f32, f32), normal: (f32, f32)) -> (f32, f32) {
+    let dot = vel.0 * normal.0 + vel.1 * normal.1;
+    if dot >= 0.0 {
+        return vel;
+    }
+    (vel.0 - 2.0 * dot * normal.0, vel.1 - 2.0 * dot * normal.1)
+}
@@ -0,0 +1,170 @@
+//! Computer player.
+//!
+//! The CPU works out where the ball will cross its paddle, bouncing it off
+//! the top and bottom walls on the way, and moves there. How late it
+//! notices the ball, how far off its guess is and how fast its paddle goes
+//! all depend on the difficulty.
+
+use rand::{self, Rng};
+
+use court::Ball;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Difficulty {
+    Easy,
+    Normal,
+    Hard,
+}
+
+impl Difficulty {
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Difficulty::Easy => "Easy",
+            Difficulty::Normal => "Normal",
+            Difficulty::Hard => "Hard",
+        }
+    }
+
+    pub fn harder(&self) -> Difficulty {
+        match *self {
+            Difficulty::Easy => Difficulty::Normal,
+            _ => Difficulty::Hard,
+        }
+    }
+
+    pub fn easier(&self) -> Difficulty {
+        match *self {
+            Difficulty::Hard => Difficulty::Normal,
+            _ => Difficulty::Easy,
+        }
+    }
+
+    /// Seconds before the CPU reacts to the ball turning around.
+    fn reaction_delay(&self) -> f32 {
+        match *self {
+            Difficulty::Easy => 0.45,
+            Difficulty::Normal => 0.25,
+            Difficulty::Hard => 0.1,
+        }
+    }
+
+    /// Pixels its guess of where the ball lands can be off by.
+    fn prediction_error(&self) -> f32 {
+        match *self {
+            Difficulty::Easy => 70.0,
+            Difficulty::Normal => 35.0,
+            Difficulty::Hard => 8.0,
+        }
+    }
+
+    /// Paddle speed, as a share of a human player's.
+    pub fn speed(&self) -> f32 {
+        match *self {
+            Difficulty::Easy => 0.6,
+            Difficulty::Normal => 0.85,
+            Difficulty::Hard => 1.0,
+        }
+    }
+}
+
+/// What the CPU does with its paddle this frame.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Move {
+    Up,
+    Down,
+    Stop,
+}
+
+pub struct Cpu {
+    difficulty: Difficulty,
+    /// Where the ball's center is when it reaches the paddle.
+    hit_x: f32,
+    court_h: f32,
+    /// Whether the ball was coming this way last time it looked.
+    ball_coming: bool,
+    /// Seconds left before it reacts to the ball turning around.
+    reaction: f32,
+    /// Height it is moving its paddle's middle to.
+    target: Option<f32>,
+    last_move: Move,
+}
+
+impl Cpu {
+    /// A CPU whose paddle the ball's center meets at `hit_x`, on a court
+    /// `court_h` pixels high.
+    pub fn new(difficulty: Difficulty, hit_x: f32, court_h: f32) -> Cpu {
+        Cpu {
+            difficulty,
+            hit_x,
+            court_h,
+            ball_coming: false,
+            reaction: 0.0,
+            target: None,
+            last_move: Move::Stop,
+        }
+    }
+
+    /// Move for a paddle with its middle at `paddle_y`, `dt` seconds after
+    /// the last one.
+    pub fn update(&mut self, dt: f32, ball: &Ball, paddle_y: f32) -> Move {
+        let coming = (self.hit_x - ball.x) * ball.vel_x > 0.0;
+        if coming != self.ball_coming {
+            self.ball_coming = coming;
+            self.reaction = self.difficulty.reaction_delay();
+            self.target = None;
+        }
+        if self.reaction > 0.0 {
+            self.reaction -= dt;
+            return self.last_move;
+        }
+
+        let target = match self.target {
+            Some(target) => target,
+            None => {
+                // going back to the middle is good enough while the ball is away
+                let target = if coming {
+                    let error = self.difficulty.prediction_error();
+                    let guess = predict_y(ball, self.hit_x, self.court_h);
+                    guess + rand::thread_rng().gen_range(-error, error)
+                } else {
+                    self.court_h / 2.0
+                };
+                self.target = Some(target);
+                target
+            }
+        };
+
+        // close enough is left alone, or the paddle would shake
+        let slack = self.difficulty.prediction_error().max(12.0) / 2.0;
+        self.last_move = if paddle_y < target - slack {
+            Move::Down
+        } else if paddle_y > target + slack {
+            Move::Up
+        } else {
+            Move::Stop
+        };
+        self.last_move
+    }
+}
+
+/// Height of the ball's center once it gets to `x`, bouncing off the top
+/// and bottom of a court `court_h` high. The ball must be heading for `x`.
+pub fn predict_y(ball: &Ball, x: f32, court_h: f32) -> f32 {
+    if ball.vel_x == 0.0 {
+        return ball.y;
+    }
+    let time = (x - ball.x) / ball.vel_x;
+    let top = ball.radius;
+    let span = court_h - 2.0 * ball.radius;
+    if span <= 0.0 {
+        return court_h / 2.0;
+    }
+
+    // unfold the bounces: every span travelled flips the direction
+    let travelled = (ball.y - top + ball.vel_y * time).rem_euclid(2.0 * span);
+    if travelled > span {
+        top + 2.0 * span - travelled
+    } else {
+        top + travelled
+    }
+}
@@ -0,0 +1,13 @@
+//! Pong rules that run without a window.
+//!
+//! The ggez frontend in `main.rs` draws the ball and paddles and feeds them
+//! key presses, the ball physics, the CPU player and the network protocol
+//! live here so they can be stepped from tests.
+
+extern crate rand;
+
+pub mod court;
+pub mod cpu;
+pub mod net;
+pub mod options;
+pub mod serve;
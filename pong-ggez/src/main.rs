@@ -1,72 +1,75 @@
 extern crate ggez;
+extern crate pong_ggez;
 extern crate rand;
 
 use ggez::event::{Keycode, Mod};
 use ggez::{conf, event, graphics, timer, Context, ContextBuilder, GameResult};
 use ggez::graphics::{DrawMode, Point2};
 
-use std::{env, path};
-use std::time::Duration;
+use std::{env, path, process};
 
 use rand::Rng;
 
+use pong_ggez::court::{Ball, Rect};
+use pong_ggez::cpu::{Cpu, Difficulty, Move};
+use pong_ggez::net::{Interpolation, Link, Message, PaddleMotion, Prediction, Snapshot};
+use pong_ggez::options::{self, Options};
+use pong_ggez::serve::{self, PlayerSide, Serve};
+
 const WINDOW_W: u32 = 900;
 const WINDOW_H: u32 = 700;
 
 const PLAYER_W: f32 = 32.0;
 const PLAYER_H: f32 = 128.0;
-const PLAYER_SPEED: f32 = 3.5;
-const BALL_ACC: f32 = 0.2;
-
-struct Ball {
-    x: f32,
-    y: f32,
-    vel_x: f32,
-    vel_y: f32,
-    radius: f32,
-    // sprite: graphics::Image,
-}
-
-impl Ball {
-    fn new(_ctx: &mut Context) -> Ball {
-        let mut rng = rand::thread_rng();
-        let mut vel_x = rng.gen::<f32>();
-        vel_x += 2.0;
-        let vel_y = rng.gen::<f32>();
-
-        Ball {
-            x: WINDOW_W as f32 / 2.0,
-            y: WINDOW_H as f32 / 2.0,
-            vel_x: vel_x,
-            vel_y: vel_y,
-            radius: 10.0,
-        }
-    }
-
-    pub fn update(&mut self) {
-        // called every frame
-        self.x += self.vel_x;
-        self.y += self.vel_y;
-    }
-
-    pub fn draw(&mut self, ctx: &mut Context) -> GameResult<()> {
-        let dst = Point2::new(self.x, self.y);
-        graphics::circle(ctx, DrawMode::Fill, dst, self.radius, 1.0)?;
-        Ok(())
-    }
+// speeds are in pixels per second
+const PLAYER_SPEED: f32 = 210.0;
+/// A served ball goes at least this fast sideways, up to `BALL_SPREAD`
+/// faster, and up to `BALL_SPREAD` up or down.
+const SERVE_SPEED: f32 = 60.0;
+const BALL_SPREAD: f32 = 60.0;
+/// Seconds "Point!" shows after a goal.
+const POINT_TIME: f32 = 1.0;
+/// Seconds counted down before a serve.
+const COUNTDOWN_TIME: f32 = 3.0;
+
+/// The game moves on in steps of this many seconds, however fast the
+/// screen refreshes, so a match plays the same on any machine.
+const STEP: f32 = 1.0 / 120.0;
+/// Frames slower than this are slowed down, rather than catching up with
+/// a burst of steps.
+const MAX_FRAME_TIME: f32 = 0.25;
+
+/// How the client's paddle moves, worked out the same on both ends of a
+/// network game.
+const PADDLE_MOTION: PaddleMotion = PaddleMotion {
+    speed: PLAYER_SPEED,
+    max_y: WINDOW_H as f32 - PLAYER_H,
+};
+/// Seconds between hellos while joining a game.
+const HELLO_TIME: f32 = 0.5;
+
+/// A ball waiting in the middle to be served.
+fn new_ball() -> Ball {
+    Ball::new(WINDOW_W as f32 / 2.0, WINDOW_H as f32 / 2.0)
 }
 
-enum PlayerSide {
-    Left,
-    Right,
+/// Draws the ball `alpha` of the way from its last step to this one.
+fn draw_ball(ctx: &mut Context, ball: &Ball, alpha: f32) -> GameResult<()> {
+    let x = lerp(ball.prev_x, ball.x, alpha);
+    let y = lerp(ball.prev_y, ball.y, alpha);
+    let dst = Point2::new(x, y);
+    graphics::circle(ctx, DrawMode::Fill, dst, ball.radius, 1.0)?;
+    Ok(())
 }
 
 struct Player {
     side: PlayerSide,
     x: f32,
     y: f32,
+    prev_y: f32,
     vel_y: f32,
     moving: bool,
+    speed: f32,
 }
 
 impl Player {
@@ -77,16 +80,18 @@ impl Player {
                 PlayerSide::Right => WINDOW_W as f32 - 40.0,
             },
             y: 300.0,
+            prev_y: 300.0,
             side: side,
             vel_y: 0.0,
             moving: false,
+            speed: PLAYER_SPEED,
         }
     }
 
-    pub fn update(&mut self) {
-        // called every frame
+    pub fn update(&mut self, dt: f32) {
+        self.prev_y = self.y;
         if self.moving {
-            self.y += self.vel_y;
+            self.y += self.vel_y * dt;
         }
         if self.y <= 0.0 {
             self.y = 0.0;
@@ -96,25 +101,99 @@ impl Player {
         }
     }
 
-    pub fn draw(&mut self, ctx: &mut Context) -> GameResult<()> {
-        let rect = graphics::Rect::new(self.x, self.y, PLAYER_W, PLAYER_H);
+    pub fn draw(&mut self, ctx: &mut Context, alpha: f32) -> GameResult<()> {
+        let y = lerp(self.prev_y, self.y, alpha);
+        let rect = graphics::Rect::new(self.x, y, PLAYER_W, PLAYER_H);
         graphics::rectangle(ctx, DrawMode::Fill, rect)?;
         Ok(())
     }
 
+    /// What the ball bounces off.
+    fn rect(&self) -> Rect {
+        Rect::new(self.x, self.y, PLAYER_W, PLAYER_H)
+    }
+
     pub fn move_up(&mut self) {
-        self.vel_y = -PLAYER_SPEED;
+        self.vel_y = -self.speed;
         self.moving = true;
     }
 
     pub fn move_down(&mut self) {
-        self.vel_y = PLAYER_SPEED;
+        self.vel_y = self.speed;
         self.moving = true;
     }
 
     pub fn stop(&mut self) {
         self.moving = false;
     }
+
+    /// What the keys held down do, as a CPU move.
+    fn held_move(&self) -> Move {
+        if !self.moving {
+            Move::Stop
+        } else if self.vel_y < 0.0 {
+            Move::Up
+        } else {
+            Move::Down
+        }
+    }
+
+    fn steer(&mut self, step: Move) {
+        match step {
+            Move::Up => self.move_up(),
+            Move::Down => self.move_down(),
+            Move::Stop => self.stop(),
+        }
+    }
+}
+
+#[derive(PartialEq)]
+enum Screen {
+    Menu,
+    Playing,
+    /// A network game that hasn't started or is over, with why.
+    Waiting(String),
+}
+
+/// The other end of a network game.
+enum Net {
+    /// This machine runs the game and plays the left paddle.
+    Host {
+        link: Link,
+        /// Client moves applied to the right paddle so far.
+        applied: u32,
+        tick: u32,
+    },
+    /// This machine plays the right paddle of the host's game.
+    Client {
+        link: Link,
+        prediction: Prediction,
+        view: Interpolation,
+        /// Seconds before saying hello again, while joining.
+        hello_in: f32,
+    },
+}
+
+/// Who plays, picked in the start menu.
+#[derive(Copy, Clone, PartialEq)]
+enum Mode {
+    TwoPlayers,
+    VersusCpu,
+    Demo,
+}
+
+impl Mode {
+    fn all() -> [Mode; 3] {
+        [Mode::TwoPlayers, Mode::VersusCpu, Mode::Demo]
+    }
+
+    fn name(&self) -> &'static str {
+        match *self {
+            Mode::TwoPlayers => "2 players",
+            Mode::VersusCpu => "1 player vs CPU",
+            Mode::Demo => "CPU vs CPU demo",
+        }
+    }
 }
 
 struct MainState {
@@ -125,6 +204,19 @@ struct MainState {
     player_r: Player,
     ball: Ball,
     score_display: graphics::Text,
+    font: graphics::Font,
+    title_font: graphics::Font,
+    screen: Screen,
+    /// Highlighted in the start menu.
+    mode: Mode,
+    difficulty: Difficulty,
+    /// Computer players, `None` for a paddle steered from the keyboard.
+    cpu_l: Option<Cpu>,
+    cpu_r: Option<Cpu>,
+    /// Seconds of play not simulated yet, less than a `STEP`.
+    lag: f32,
+    serve: Serve,
+    net: Option<Net>,
 }
 
 impl MainState {
@@ -132,94 +224,440 @@ impl MainState {
         let font = graphics::Font::new(ctx, "/DejaVuSerif.ttf", 18)?;
         let text_to_display = format!("Score: 0x0");
         let text = graphics::Text::new(ctx, &text_to_display, &font)?;
+        let title_font = graphics::Font::new(ctx, "/DejaVuSerif.ttf", 48)?;
         let s = MainState {
             score: (0, 0),
             hits: 0,
             score_changed: false,
             player_l: Player::new(ctx, PlayerSide::Left),
             player_r: Player::new(ctx, PlayerSide::Right),
-            ball: Ball::new(ctx),
+            ball: new_ball(),
             score_display: text,
+            font,
+            title_font,
+            screen: Screen::Menu,
+            mode: Mode::VersusCpu,
+            difficulty: Difficulty::Normal,
+            cpu_l: None,
+            cpu_r: None,
+            lag: 0.0,
+            serve: Serve::Rally,
+            net: None,
         };
         Ok(s)
     }
 
-    pub fn collision(&mut self) {
-        //ball collision with top or bottom
-        if self.ball.y - self.ball.radius <= 0.0 {
-            self.ball.vel_y *= -1.0;
-            self.ball.y += 0.1;
+    /// Starts a match in the mode picked in the menu.
+    fn start(&mut self, ctx: &mut Context) {
+        self.score = (0, 0);
+        self.hits = 0;
+        self.score_changed = true;
+        self.ball = new_ball();
+        self.player_l = Player::new(ctx, PlayerSide::Left);
+        self.player_r = Player::new(ctx, PlayerSide::Right);
+        self.lag = 0.0;
+        self.serve = Serve::Countdown {
+            receiver: PlayerSide::Right,
+            time_left: COUNTDOWN_TIME,
+        };
+
+        let court_h = WINDOW_H as f32;
+        let hit_l = self.player_l.x + PLAYER_W + self.ball.radius;
+        let hit_r = self.player_r.x - self.ball.radius;
+        self.cpu_l = match self.mode {
+            Mode::Demo => Some(Cpu::new(self.difficulty, hit_l, court_h)),
+            _ => None,
+        };
+        self.cpu_r = match self.mode {
+            Mode::TwoPlayers => None,
+            _ => Some(Cpu::new(self.difficulty, hit_r, court_h)),
+        };
+        let cpu_speed = PLAYER_SPEED * self.difficulty.speed();
+        if self.cpu_l.is_some() {
+            self.player_l.speed = cpu_speed;
         }
-        if self.ball.y + self.ball.radius >= WINDOW_H as f32 {
-            self.ball.vel_y *= -1.0;
-            self.ball.y -= 0.1;
+        if self.cpu_r.is_some() {
+            self.player_r.speed = cpu_speed;
         }
+        self.screen = Screen::Playing;
+    }
 
-        //ball collision with left and right
-        // score
-        if self.ball.x < 0.0 {
-            self.score.0 += 1;
+    /// Lets the computer players pick their moves.
+    fn update_cpus(&mut self, dt: f32) {
+        let ball = &self.ball;
+        if let Some(ref mut cpu) = self.cpu_l {
+            let step = cpu.update(dt, ball, self.player_l.y + PLAYER_H / 2.0);
+            self.player_l.steer(step);
         }
-        if self.ball.x > WINDOW_W as f32 {
-            self.score.1 += 1;
+        if let Some(ref mut cpu) = self.cpu_r {
+            let step = cpu.update(dt, ball, self.player_r.y + PLAYER_H / 2.0);
+            self.player_r.steer(step);
         }
-        // restart ball
-        if self.ball.x < 0.0 || self.ball.x > WINDOW_W as f32 {
-            let mut rng = rand::thread_rng();
-            self.ball.vel_x = rng.gen::<f32>();
-            self.ball.vel_x += 1.0;
-            self.ball.vel_y = rng.gen::<f32>();
-            self.ball.x = WINDOW_W as f32 / 2.0;
-            self.ball.y = WINDOW_H as f32 / 2.0;
-            self.score_changed = true;
-            timer::sleep(Duration::from_secs(1));
-            self.hits = 0;
+    }
+
+    /// Paddle a key steers: A and Z the left one and the arrows the right
+    /// one, or either of them the same paddle when the CPU or the other end
+    /// of a network game has the other. `None` for keys of a paddle the CPU
+    /// plays.
+    fn paddle_for(&mut self, keycode: Keycode) -> Option<&mut Player> {
+        let (host, client) = match self.net {
+            Some(Net::Host { .. }) => (true, false),
+            Some(Net::Client { .. }) => (false, true),
+            None => (false, false),
+        };
+        let left = match keycode {
+            Keycode::A | Keycode::Z => !client,
+            Keycode::Up | Keycode::Down => self.cpu_r.is_some() || host,
+            _ => return None,
+        };
+        if left && self.cpu_l.is_none() {
+            Some(&mut self.player_l)
+        } else if !left && self.cpu_r.is_none() {
+            Some(&mut self.player_r)
+        } else {
+            None
         }
+    }
 
-        //ball collision with player left
-        if self.ball.x - self.ball.radius <= self.player_l.x + PLAYER_W + 0.2
-            && self.ball.y + self.ball.radius / 2.0 >= self.player_l.y
-            && self.ball.y - self.ball.radius / 2.0 < self.player_l.y + PLAYER_H
-        {
-            let player_midy = self.player_l.y + PLAYER_H / 2.0;
-            let dif_y = self.ball.y - player_midy;
-            self.ball.vel_y += dif_y / 20.0;
+    fn menu_key(&mut self, ctx: &mut Context, keycode: Keycode) {
+        let modes = Mode::all();
+        let selected = modes.iter().position(|&m| m == self.mode).unwrap_or(0);
+        match keycode {
+            Keycode::Up => self.mode = modes[(selected + modes.len() - 1) % modes.len()],
+            Keycode::Down => self.mode = modes[(selected + 1) % modes.len()],
+            Keycode::Left => self.difficulty = self.difficulty.easier(),
+            Keycode::Right => self.difficulty = self.difficulty.harder(),
+            Keycode::Return | Keycode::Space => self.start(ctx),
+            Keycode::Escape => ctx.quit().unwrap(),
+            _ => {}
+        }
+    }
 
-            self.ball.vel_x -= BALL_ACC;
-            self.ball.vel_x *= -1.0;
-            self.hits += 1;
-            self.score_changed = true;
-            if self.ball.x <= PLAYER_W + self.player_l.x {
-                self.ball.x = PLAYER_W + self.player_l.x + 1.0 + self.ball.radius;
+    /// "Point!" over the half that scored, or the seconds left before the
+    /// serve over the middle.
+    fn draw_serve(&mut self, ctx: &mut Context) -> GameResult<()> {
+        let (line, x) = match self.serve {
+            Serve::Point { scorer, .. } => {
+                let x = match scorer {
+                    PlayerSide::Left => WINDOW_W as f32 / 4.0,
+                    PlayerSide::Right => WINDOW_W as f32 * 3.0 / 4.0,
+                };
+                (String::from("Point!"), x)
+            }
+            Serve::Countdown { time_left, .. } => {
+                (format!("{}", time_left.ceil() as u32), WINDOW_W as f32 / 2.0)
             }
+            Serve::Rally => return Ok(()),
+        };
+        let text = graphics::Text::new(ctx, &line, &self.title_font)?;
+        let dest = Point2::new(x - text.width() as f32 / 2.0, WINDOW_H as f32 / 3.0);
+        graphics::draw(ctx, &text, dest, 0.0)?;
+        Ok(())
+    }
+
+    fn draw_menu(&mut self, ctx: &mut Context) -> GameResult<()> {
+        let x = WINDOW_W as f32 / 2.0 - 180.0;
+        let title = graphics::Text::new(ctx, "PONG", &self.title_font)?;
+        graphics::draw(ctx, &title, Point2::new(x, 120.0), 0.0)?;
+
+        let mut lines: Vec<String> = Mode::all()
+            .iter()
+            .map(|&mode| {
+                let marker = if mode == self.mode { ">" } else { " " };
+                format!("{} {}", marker, mode.name())
+            })
+            .collect();
+        lines.push(String::new());
+        lines.push(format!("CPU difficulty: < {} >", self.difficulty.name()));
+        lines.push(String::new());
+        lines.push(String::from("Up/Down picks, Left/Right sets the difficulty"));
+        lines.push(String::from("Enter starts, Esc quits or comes back here"));
+        lines.push(String::from("Left paddle A/Z, right paddle arrows"));
+
+        let mut y = 240.0;
+        for line in lines {
+            if !line.is_empty() {
+                let text = graphics::Text::new(ctx, &line, &self.font)?;
+                graphics::draw(ctx, &text, Point2::new(x, y), 0.0)?;
+            }
+            y += 32.0;
+        }
+        Ok(())
+    }
+
+    fn draw_waiting(&mut self, ctx: &mut Context, message: &str) -> GameResult<()> {
+        let x = WINDOW_W as f32 / 2.0 - 180.0;
+        let title = graphics::Text::new(ctx, "PONG", &self.title_font)?;
+        graphics::draw(ctx, &title, Point2::new(x, 120.0), 0.0)?;
+        for (i, line) in [message, "Esc quits"].iter().enumerate() {
+            let text = graphics::Text::new(ctx, line, &self.font)?;
+            graphics::draw(ctx, &text, Point2::new(x, 240.0 + 32.0 * i as f32), 0.0)?;
         }
+        Ok(())
+    }
+
+    /// Waits for the other end of a network game, and starts the match
+    /// once it answers.
+    fn poll_connection(&mut self, ctx: &mut Context, dt: f32) {
+        let mut joined = false;
+        match self.net {
+            Some(Net::Host { ref mut link, .. }) => {
+                joined = link.receive().contains(&Message::Hello);
+            }
+            Some(Net::Client {
+                ref mut link,
+                ref mut view,
+                ref mut hello_in,
+                ..
+            }) => {
+                *hello_in -= dt;
+                if *hello_in <= 0.0 {
+                    link.send(&Message::Hello);
+                    *hello_in = HELLO_TIME;
+                }
+                for message in link.receive() {
+                    if let Message::State(state) = message {
+                        view.push(state);
+                        joined = true;
+                    }
+                }
+            }
+            None => {}
+        }
+        if joined {
+            self.mode = Mode::TwoPlayers;
+            self.start(ctx);
+        }
+    }
 
-        //ball collision with player right
-        if self.ball.x + self.ball.radius >= self.player_r.x - 0.2
-            && self.ball.y + self.ball.radius / 2.0 > self.player_r.y
-            && self.ball.y - self.ball.radius / 2.0 < self.player_r.y + PLAYER_H
+    /// Leaves a network game, telling the other end in case it's still
+    /// there.
+    fn hang_up(&mut self) {
+        if let Some(Net::Host { ref mut link, .. }) | Some(Net::Client { ref mut link, .. }) = self.net {
+            link.send(&Message::Bye);
+        }
+        self.net = None;
+    }
+
+    fn disconnect(&mut self, reason: &str) {
+        self.hang_up();
+        self.screen = Screen::Waiting(String::from(reason));
+    }
+
+    /// Moves the game on by one `STEP`.
+    fn step(&mut self) {
+        if let Some(Net::Client { .. }) = self.net {
+            self.client_step();
+            return;
+        }
+        self.update_cpus(STEP);
+        self.player_l.update(STEP);
+        self.player_r.update(STEP);
+        // after the update has saved prev_y, so the client's paddle glides
+        self.host_receive();
+        match self.serve {
+            Serve::Rally => {
+                let paddles = [self.player_l.rect(), self.player_r.rect()];
+                let hits = self.ball.update(STEP, WINDOW_H as f32, &paddles);
+                if !hits.is_empty() {
+                    self.hits += hits.len() as u32;
+                    self.score_changed = true;
+                }
+                self.check_goal();
+            }
+            _ => self.update_serve(STEP),
+        }
+        self.host_send();
+    }
+
+    /// Moves the client's paddle with the moves it sent.
+    fn host_receive(&mut self) {
+        let mut left = false;
+        if let Some(Net::Host {
+            ref mut link,
+            ref mut applied,
+            ..
+        }) = self.net
         {
-            let player_midy = self.player_r.y + PLAYER_H / 2.0;
-            let dif_y = self.ball.y - player_midy;
-            self.ball.vel_y += dif_y / 30.0;
+            for message in link.receive() {
+                match message {
+                    Message::Input { first, moves } => {
+                        for (number, &step) in (first..).zip(moves.iter()) {
+                            if number >= *applied {
+                                self.player_r.y = PADDLE_MOTION.apply(self.player_r.y, step, STEP);
+                                *applied = number + 1;
+                            }
+                        }
+                    }
+                    Message::Bye => left = true,
+                    _ => {}
+                }
+            }
+            left = left || link.timed_out();
+        }
+        if left {
+            self.disconnect("The other player left");
+        }
+    }
 
-            self.ball.vel_x += BALL_ACC;
-            self.ball.vel_x *= -1.0;
-            self.hits += 1;
-            self.score_changed = true;
-            if self.ball.x >= self.player_r.x {
-                self.ball.x = self.player_r.x - 1.0 - self.ball.radius;
+    /// Sends the client the game as it is after this step.
+    fn host_send(&mut self) {
+        if let Some(Net::Host {
+            ref mut link,
+            applied,
+            ref mut tick,
+        }) = self.net
+        {
+            *tick += 1;
+            link.send(&Message::State(Snapshot {
+                tick: *tick,
+                ack: applied,
+                ball: (self.ball.x, self.ball.y),
+                ball_vel: (self.ball.vel_x, self.ball.vel_y),
+                left_y: self.player_l.y,
+                right_y: self.player_r.y,
+                score: self.score,
+                hits: self.hits,
+                serve: self.serve,
+            }));
+        }
+    }
+
+    /// A step of the client: its own paddle moves right away, the rest is
+    /// what the host sent, a little behind.
+    fn client_step(&mut self) {
+        let mut left = false;
+        let mut shown = None;
+        if let Some(Net::Client {
+            ref mut link,
+            ref mut prediction,
+            ref mut view,
+            ..
+        }) = self.net
+        {
+            let step = self.player_r.held_move();
+            self.player_r.prev_y = self.player_r.y;
+            self.player_r.y = prediction.step(self.player_r.y, step, STEP);
+            link.send(&prediction.input());
+
+            for message in link.receive() {
+                match message {
+                    // states older than one already here are dropped
+                    Message::State(state) if view.push(state) => {
+                        self.player_r.y = prediction.reconcile(state.ack, state.right_y, STEP);
+                    }
+                    Message::Bye => left = true,
+                    _ => {}
+                }
             }
+            left = left || link.timed_out();
+            view.advance(1.0);
+            shown = view.sample();
+        }
+        if let Some(state) = shown {
+            self.show(state);
+        }
+        if left {
+            self.disconnect("The host left");
+        }
+    }
+
+    /// Puts the ball, the host's paddle and the score where the host had
+    /// them.
+    fn show(&mut self, state: Snapshot) {
+        if state.serve == Serve::Rally {
+            self.ball.prev_x = self.ball.x;
+            self.ball.prev_y = self.ball.y;
+            self.ball.x = state.ball.0;
+            self.ball.y = state.ball.1;
+        } else {
+            self.ball.place(state.ball.0, state.ball.1);
+        }
+        self.ball.vel_x = state.ball_vel.0;
+        self.ball.vel_y = state.ball_vel.1;
+        self.player_l.prev_y = self.player_l.y;
+        self.player_l.y = state.left_y;
+        if state.score != self.score || state.hits != self.hits {
+            self.score_changed = true;
         }
+        self.score = state.score;
+        self.hits = state.hits;
+        self.serve = state.serve;
+    }
+
+    /// Counts the time between a goal and the next serve.
+    fn update_serve(&mut self, dt: f32) {
+        self.serve = match self.serve {
+            Serve::Point { scorer, time_left } if time_left > dt => Serve::Point {
+                scorer,
+                time_left: time_left - dt,
+            },
+            Serve::Point { scorer, .. } => {
+                self.ball.place(WINDOW_W as f32 / 2.0, WINDOW_H as f32 / 2.0);
+                Serve::Countdown {
+                    receiver: scorer.other(),
+                    time_left: COUNTDOWN_TIME,
+                }
+            }
+            Serve::Countdown { receiver, time_left } if time_left > dt => Serve::Countdown {
+                receiver,
+                time_left: time_left - dt,
+            },
+            Serve::Countdown { receiver, .. } => {
+                self.launch(receiver);
+                Serve::Rally
+            }
+            Serve::Rally => Serve::Rally,
+        };
+    }
+
+    /// Sends the ball from the middle toward `receiver`.
+    fn launch(&mut self, receiver: PlayerSide) {
+        let mut rng = rand::thread_rng();
+        let speed = SERVE_SPEED + rng.gen::<f32>() * BALL_SPREAD;
+        self.ball.vel_x = match receiver {
+            PlayerSide::Left => -speed,
+            PlayerSide::Right => speed,
+        };
+        self.ball.vel_y = rng.gen_range(-BALL_SPREAD, BALL_SPREAD);
+    }
+
+    /// Scores a ball that left the court and waits for the next serve.
+    fn check_goal(&mut self) {
+        let scorer = match serve::scorer(self.ball.x, WINDOW_W as f32) {
+            Some(scorer) => scorer,
+            None => return,
+        };
+        serve::add_point(&mut self.score, scorer);
+        self.ball.vel_x = 0.0;
+        self.ball.vel_y = 0.0;
+        self.score_changed = true;
+        self.hits = 0;
+        self.serve = Serve::Point {
+            scorer,
+            time_left: POINT_TIME,
+        };
     }
 }
 
 impl event::EventHandler for MainState {
     fn update(&mut self, ctx: &mut Context) -> GameResult<()> {
-        self.player_l.update();
-        self.player_r.update();
-        self.ball.update();
-        self.collision();
+        let frame_time = timer::duration_to_f64(timer::get_delta(ctx)) as f32;
+        match self.screen {
+            Screen::Menu => return Ok(()),
+            Screen::Waiting(_) => {
+                self.poll_connection(ctx, frame_time);
+                return Ok(());
+            }
+            Screen::Playing => {}
+        }
+
+        self.lag += frame_time.min(MAX_FRAME_TIME);
+        // a network game can end part way through
+        while self.lag >= STEP && self.screen == Screen::Playing {
+            self.step();
+            self.lag -= STEP;
+        }
 
         // new score text
         if self.score_changed {
@@ -239,6 +677,18 @@ impl event::EventHandler for MainState {
     fn draw(&mut self, ctx: &mut Context) -> GameResult<()> {
         graphics::clear(ctx);
 
+        if self.screen == Screen::Menu {
+            self.draw_menu(ctx)?;
+            graphics::present(ctx);
+            return Ok(());
+        }
+        if let Screen::Waiting(ref message) = self.screen {
+            let message = message.clone();
+            self.draw_waiting(ctx, &message)?;
+            graphics::present(ctx);
+            return Ok(());
+        }
+
         let mid_rect = graphics::Rect::new(
             WINDOW_W as f32 / 2.0 - 5.0,
             10.0,
@@ -247,49 +697,106 @@ impl event::EventHandler for MainState {
         );
         graphics::rectangle(ctx, DrawMode::Line(1.0), mid_rect)?;
 
-        self.player_l.draw(ctx)?;
-        self.player_r.draw(ctx)?;
-        self.ball.draw(ctx)?;
+        // in between the last two steps, for a smooth picture at any refresh rate
+        let alpha = self.lag / STEP;
+        self.player_l.draw(ctx, alpha)?;
+        self.player_r.draw(ctx, alpha)?;
+        // after a goal the ball is off the court until it is put back
+        if !matches!(self.serve, Serve::Point { .. }) {
+            draw_ball(ctx, &self.ball, alpha)?;
+        }
         //score
         let dest_point = Point2::new(50.0, 20.0);
         graphics::draw(ctx, &self.score_display, dest_point, 0.0)?;
+        self.draw_serve(ctx)?;
 
         graphics::present(ctx);
         Ok(())
     }
 
     fn key_up_event(&mut self, _ctx: &mut ggez::Context, keycode: Keycode, _: Mod, _: bool) {
-        match keycode {
-            Keycode::A | Keycode::Z => {
-                self.player_l.stop();
-            }
-            Keycode::Up | Keycode::Down => {
-                self.player_r.stop();
-            }
-            _ => {}
+        if self.screen != Screen::Playing {
+            return;
+        }
+        if let Some(player) = self.paddle_for(keycode) {
+            player.stop();
         }
     }
 
-    fn key_down_event(&mut self, _ctx: &mut ggez::Context, keycode: Keycode, _: Mod, _: bool) {
-        match keycode {
-            Keycode::A => {
-                self.player_l.move_up();
-            }
-            Keycode::Z => {
-                self.player_l.move_down();
+    fn key_down_event(&mut self, ctx: &mut ggez::Context, keycode: Keycode, _: Mod, _: bool) {
+        if self.screen == Screen::Menu {
+            self.menu_key(ctx, keycode);
+            return;
+        }
+        if keycode == Keycode::Escape {
+            // a network game has no menu to go back to
+            if self.screen == Screen::Playing && self.net.is_none() {
+                self.screen = Screen::Menu;
+            } else {
+                self.hang_up();
+                ctx.quit().unwrap();
             }
-            Keycode::Up => {
-                self.player_r.move_up();
+            return;
+        }
+        if self.screen != Screen::Playing {
+            return;
+        }
+        match keycode {
+            Keycode::A | Keycode::Up => {
+                if let Some(player) = self.paddle_for(keycode) {
+                    player.move_up();
+                }
             }
-            Keycode::Down => {
-                self.player_r.move_down();
+            Keycode::Z | Keycode::Down => {
+                if let Some(player) = self.paddle_for(keycode) {
+                    player.move_down();
+                }
             }
             _ => {}
         }
     }
 }
 
+/// Opens the socket for `--host` or `--join`, `None` for a game on this
+/// machine.
+fn connect(options: &Options) -> Result<Option<Net>, String> {
+    let conditions = options.conditions();
+    if let Some(port) = options.host {
+        let link = Link::host(port, conditions).map_err(|e| format!("can't host on port {}: {}", port, e))?;
+        return Ok(Some(Net::Host {
+            link,
+            applied: 0,
+            tick: 0,
+        }));
+    }
+    if let Some(ref address) = options.join {
+        let link = Link::join(address, conditions).map_err(|e| format!("can't join {}: {}", address, e))?;
+        return Ok(Some(Net::Client {
+            link,
+            prediction: Prediction::new(PADDLE_MOTION),
+            view: Interpolation::new(),
+            hello_in: 0.0,
+        }));
+    }
+    Ok(None)
+}
+
 pub fn main() {
+    let options = Options::parse_args(env::args().skip(1))
+        .and_then(|options| options.validate().map(|_| options))
+        .unwrap_or_else(|e| {
+            println!("{}\n\n{}", e, options::USAGE);
+            process::exit(2);
+        });
+    if options.help {
+        println!("{}", options::USAGE);
+        return;
+    }
+    let net = connect(&options).unwrap_or_else(|e| {
+        println!("{}", e);
+        process::exit(1);
+    });
+
     let mut cb = ContextBuilder::new("classic", "ggez")
         .window_setup(conf::WindowSetup::default().title("Pong"))
         .window_mode(conf::WindowMode::default().dimensions(WINDOW_W, WINDOW_H));
@@ -304,5 +811,16 @@ pub fn main() {
 
     let ctx = &mut cb.build().unwrap();
     let state = &mut MainState::new(ctx).unwrap();
+    if let Some(net) = net {
+        state.screen = Screen::Waiting(match net {
+            Net::Host { .. } => format!("Waiting for a player to join on port {}", options.host.unwrap()),
+            Net::Client { .. } => format!("Joining {}", options.join.as_ref().unwrap()),
+        });
+        state.net = Some(net);
+    }
     event::run(ctx, state).unwrap();
 }
+
+fn lerp(from: f32, to: f32, alpha: f32) -> f32 {
+    from + (to - from) * alpha
+}
@@ -0,0 +1,505 @@
+//! Playing over the network.
+//!
+//! The host runs the game: it moves the ball, keeps the score and sends
+//! the whole state to the client every step. The client only sends its
+//! paddle moves, numbered, and repeats the ones the host hasn't confirmed
+//! yet, so a lost packet costs nothing once the next one arrives.
+//!
+//! The client doesn't wait for the host to see its own paddle move: it
+//! moves it right away and, when a state comes back, starts again from
+//! where the host put it and replays the moves the host hadn't seen yet.
+//! Everything else is shown a little in the past, in between the last two
+//! states, so it glides instead of jumping from packet to packet.
+//!
+//! Every packet starts with `PG`, the protocol version and its kind, then
+//! its fields in little endian.
+
+use std::collections::VecDeque;
+use std::io::{self, ErrorKind};
+use std::net::{SocketAddr, ToSocketAddrs, UdpSocket};
+use std::time::{Duration, Instant};
+
+use rand::{self, Rng};
+
+use cpu::Move;
+use serve::{PlayerSide, Serve};
+
+const MAGIC: &[u8; 2] = b"PG";
+const VERSION: u8 = 1;
+/// Moves sent again in each input, at most.
+pub const MAX_INPUTS: usize = 64;
+/// Most milliseconds `--latency` holds packets back for.
+pub const MAX_LATENCY: u64 = 2000;
+/// Moves kept waiting for the host: a round trip with `MAX_LATENCY` each
+/// way at 120 steps a second, and a second to spare.
+pub const MAX_PENDING: usize = (2 * MAX_LATENCY as usize + 1000) * 120 / 1000;
+/// Host steps the client shows behind the newest state it has.
+pub const INTERPOLATION_DELAY: f32 = 8.0;
+/// Further off than this and the shown time jumps, rather than drifts.
+const MAX_DRIFT: f32 = 30.0;
+/// Share of the way to the right delay the shown time drifts each step.
+const CATCH_UP: f32 = 0.05;
+/// Nothing heard for this long and the other player is gone.
+const TIMEOUT: Duration = Duration::from_secs(5);
+const MAX_PACKET: usize = 512;
+
+/// The game as the host sends it.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Snapshot {
+    /// Host steps since the match started.
+    pub tick: u32,
+    /// Client moves the host has applied, their numbers start at 0.
+    pub ack: u32,
+    pub ball: (f32, f32),
+    pub ball_vel: (f32, f32),
+    /// Tops of the paddles.
+    pub left_y: f32,
+    pub right_y: f32,
+    pub score: (u32, u32),
+    pub hits: u32,
+    pub serve: Serve,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Message {
+    /// A client asking to play, sent until the first state comes back.
+    Hello,
+    /// Client paddle moves, numbered on from `first`.
+    Input { first: u32, moves: Vec<Move> },
+    State(Snapshot),
+    /// The other player quit.
+    Bye,
+}
+
+impl Message {
+    pub fn encode(&self) -> Vec<u8> {
+        let mut bytes = MAGIC.to_vec();
+        bytes.push(VERSION);
+        match *self {
+            Message::Hello => bytes.push(0),
+            Message::Input { first, ref moves } => {
+                bytes.push(1);
+                put_u32(&mut bytes, first);
+                let moves = &moves[..moves.len().min(MAX_INPUTS)];
+                bytes.push(moves.len() as u8);
+                bytes.extend(moves.iter().map(|&step| move_to_byte(step)));
+            }
+            Message::State(ref state) => {
+                bytes.push(2);
+                put_u32(&mut bytes, state.tick);
+                put_u32(&mut bytes, state.ack);
+                for &value in &[state.ball.0, state.ball.1, state.ball_vel.0, state.ball_vel.1] {
+                    put_f32(&mut bytes, value);
+                }
+                put_f32(&mut bytes, state.left_y);
+                put_f32(&mut bytes, state.right_y);
+                put_u32(&mut bytes, state.score.0);
+                put_u32(&mut bytes, state.score.1);
+                put_u32(&mut bytes, state.hits);
+                let (kind, side, time_left) = match state.serve {
+                    Serve::Rally => (0, PlayerSide::Left, 0.0),
+                    Serve::Point { scorer, time_left } => (1, scorer, time_left),
+                    Serve::Countdown { receiver, time_left } => (2, receiver, time_left),
+                };
+                bytes.push(kind);
+                bytes.push(if side == PlayerSide::Left { 0 } else { 1 });
+                put_f32(&mut bytes, time_left);
+            }
+            Message::Bye => bytes.push(3),
+        }
+        bytes
+    }
+
+    /// `None` for anything that isn't a whole packet of this version.
+    pub fn decode(bytes: &[u8]) -> Option<Message> {
+        if bytes.len() < 4 || &bytes[..2] != MAGIC || bytes[2] != VERSION {
+            return None;
+        }
+        let mut reader = Reader { bytes, at: 4 };
+        let message = match bytes[3] {
+            0 => Message::Hello,
+            1 => {
+                let first = reader.u32()?;
+                let count = reader.u8()?;
+                let moves = (0..count)
+                    .map(|_| reader.u8().and_then(move_from_byte))
+                    .collect::<Option<Vec<Move>>>()?;
+                Message::Input { first, moves }
+            }
+            2 => {
+                let tick = reader.u32()?;
+                let ack = reader.u32()?;
+                let ball = (reader.f32()?, reader.f32()?);
+                let ball_vel = (reader.f32()?, reader.f32()?);
+                let left_y = reader.f32()?;
+                let right_y = reader.f32()?;
+                let score = (reader.u32()?, reader.u32()?);
+                let hits = reader.u32()?;
+                let kind = reader.u8()?;
+                let side = match reader.u8()? {
+                    0 => PlayerSide::Left,
+                    1 => PlayerSide::Right,
+                    _ => return None,
+                };
+                let time_left = reader.f32()?;
+                let serve = match kind {
+                    0 => Serve::Rally,
+                    1 => Serve::Point { scorer: side, time_left },
+                    2 => Serve::Countdown { receiver: side, time_left },
+                    _ => return None,
+                };
+                Message::State(Snapshot {
+                    tick,
+                    ack,
+                    ball,
+                    ball_vel,
+                    left_y,
+                    right_y,
+                    score,
+                    hits,
+                    serve,
+                })
+            }
+            3 => Message::Bye,
+            _ => return None,
+        };
+        if reader.at != bytes.len() {
+            return None;
+        }
+        Some(message)
+    }
+}
+
+fn put_u32(bytes: &mut Vec<u8>, value: u32) {
+    bytes.extend_from_slice(&value.to_le_bytes());
+}
+
+fn put_f32(bytes: &mut Vec<u8>, value: f32) {
+    put_u32(bytes, value.to_bits());
+}
+
+fn move_to_byte(step: Move) -> u8 {
+    match step {
+        Move::Stop => 0,
+        Move::Up => 1,
+        Move::Down => 2,
+    }
+}
+
+fn move_from_byte(byte: u8) -> Option<Move> {
+    match byte {
+        0 => Some(Move::Stop),
+        1 => Some(Move::Up),
+        2 => Some(Move::Down),
+        _ => None,
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    at: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn u8(&mut self) -> Option<u8> {
+        let byte = *self.bytes.get(self.at)?;
+        self.at += 1;
+        Some(byte)
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        let bytes = self.bytes.get(self.at..self.at + 4)?;
+        self.at += 4;
+        Some(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    fn f32(&mut self) -> Option<f32> {
+        self.u32().map(f32::from_bits)
+    }
+}
+
+/// How a paddle moves, the same on the host and the client so both work
+/// out the same place from the same moves.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct PaddleMotion {
+    /// Pixels per second.
+    pub speed: f32,
+    /// Lowest the top of the paddle goes, the highest is 0.
+    pub max_y: f32,
+}
+
+impl PaddleMotion {
+    /// Where a paddle at `y` is after making `step` for `dt` seconds.
+    pub fn apply(&self, y: f32, step: Move, dt: f32) -> f32 {
+        let y = match step {
+            Move::Up => y - self.speed * dt,
+            Move::Down => y + self.speed * dt,
+            Move::Stop => y,
+        };
+        y.max(0.0).min(self.max_y)
+    }
+}
+
+/// The client's own paddle, moved ahead of the host.
+pub struct Prediction {
+    motion: PaddleMotion,
+    /// Moves the host hasn't applied yet, with their numbers.
+    pending: VecDeque<(u32, Move)>,
+    next: u32,
+    /// Where the paddle was last put.
+    y: f32,
+}
+
+impl Prediction {
+    pub fn new(motion: PaddleMotion) -> Prediction {
+        Prediction {
+            motion,
+            pending: VecDeque::new(),
+            next: 0,
+            y: 0.0,
+        }
+    }
+
+    /// Records the move made this step, returning where it takes the
+    /// paddle from `y`.
+    pub fn step(&mut self, y: f32, step: Move, dt: f32) -> f32 {
+        self.pending.push_back((self.next, step));
+        self.next += 1;
+        if self.pending.len() > MAX_PENDING {
+            self.pending.pop_front();
+        }
+        self.y = self.motion.apply(y, step, dt);
+        self.y
+    }
+
+    /// The newest moves the host hasn't applied yet, to send it.
+    pub fn input(&self) -> Message {
+        let skip = self.pending.len().saturating_sub(MAX_INPUTS);
+        let first = self.pending.get(skip).map_or(self.next, |&(number, _)| number);
+        Message::Input {
+            first,
+            moves: self.pending.iter().skip(skip).map(|&(_, step)| step).collect(),
+        }
+    }
+
+    /// Where the paddle is now, given the host had it at `host_y` after its
+    /// first `ack` moves. When moves the host hasn't applied yet were
+    /// dropped there is nothing to replay from, the paddle stays put.
+    pub fn reconcile(&mut self, ack: u32, host_y: f32, dt: f32) -> f32 {
+        while self.pending.front().is_some_and(|&(number, _)| number < ack) {
+            self.pending.pop_front();
+        }
+        let first = self.pending.front().map_or(self.next, |&(number, _)| number);
+        if first > ack {
+            return self.y;
+        }
+        self.y = self
+            .pending
+            .iter()
+            .fold(host_y, |y, &(_, step)| self.motion.apply(y, step, dt));
+        self.y
+    }
+}
+
+/// The host's states, shown a little behind the newest one.
+pub struct Interpolation {
+    snapshots: VecDeque<Snapshot>,
+    /// Host tick shown, with a fraction to be in between two states.
+    tick: f32,
+}
+
+impl Default for Interpolation {
+    fn default() -> Interpolation {
+        Interpolation::new()
+    }
+}
+
+impl Interpolation {
+    pub fn new() -> Interpolation {
+        Interpolation {
+            snapshots: VecDeque::new(),
+            tick: 0.0,
+        }
+    }
+
+    /// Keeps a state from the host. Returns false, and drops it, when it is
+    /// no newer than one already here.
+    pub fn push(&mut self, snapshot: Snapshot) -> bool {
+        match self.snapshots.back() {
+            Some(newest) if snapshot.tick <= newest.tick => return false,
+            Some(_) => {}
+            None => self.tick = snapshot.tick as f32 - INTERPOLATION_DELAY,
+        }
+        self.snapshots.push_back(snapshot);
+        true
+    }
+
+    /// Moves the shown time on by `ticks`, drifting toward
+    /// `INTERPOLATION_DELAY` behind the newest state.
+    pub fn advance(&mut self, ticks: f32) {
+        self.tick += ticks;
+        if let Some(newest) = self.snapshots.back() {
+            let behind = newest.tick as f32 - INTERPOLATION_DELAY - self.tick;
+            if behind.abs() > MAX_DRIFT {
+                self.tick += behind;
+            } else {
+                self.tick += behind * CATCH_UP;
+            }
+        }
+        // the older of the two states around the shown time is kept
+        while self.snapshots.len() > 2 && self.snapshots[1].tick as f32 <= self.tick {
+            self.snapshots.pop_front();
+        }
+    }
+
+    /// The game at the shown time. The ball and paddles are moved in
+    /// between the states around it while the ball is in play, everything
+    /// else is the older state's.
+    pub fn sample(&self) -> Option<Snapshot> {
+        let older = *self.snapshots.front()?;
+        let newer = match self.snapshots.get(1) {
+            Some(newer) if self.tick > older.tick as f32 => *newer,
+            _ => return Some(older),
+        };
+        if self.tick >= newer.tick as f32 {
+            return Some(newer);
+        }
+        if older.serve != Serve::Rally || newer.serve != Serve::Rally {
+            return Some(older);
+        }
+
+        let alpha = (self.tick - older.tick as f32) / (newer.tick - older.tick) as f32;
+        let lerp = |from: f32, to: f32| from + (to - from) * alpha;
+        Some(Snapshot {
+            ball: (lerp(older.ball.0, newer.ball.0), lerp(older.ball.1, newer.ball.1)),
+            left_y: lerp(older.left_y, newer.left_y),
+            right_y: lerp(older.right_y, newer.right_y),
+            ..older
+        })
+    }
+}
+
+/// Pretend network trouble, for trying the game out on one machine.
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub struct Conditions {
+    /// Added to every packet sent.
+    pub latency: Duration,
+    /// Share of the packets sent that are dropped, from 0 to 1.
+    pub loss: f32,
+}
+
+/// A UDP socket talking to the one other player.
+pub struct Link {
+    socket: UdpSocket,
+    peer: Option<SocketAddr>,
+    /// Last time anything came from the peer.
+    heard: Option<Instant>,
+    conditions: Conditions,
+    /// Packets held back by the pretend latency, with when they go out.
+    delayed: VecDeque<(Instant, Vec<u8>)>,
+}
+
+impl Link {
+    /// Waits on `port` for a client. The first one to say hello is the
+    /// peer, anyone else is ignored.
+    pub fn host(port: u16, conditions: Conditions) -> io::Result<Link> {
+        let socket = UdpSocket::bind(("0.0.0.0", port))?;
+        Link::new(socket, None, conditions)
+    }
+
+    /// Talks to the host at `address`, like `192.168.0.2:4000`.
+    pub fn join(address: &str, conditions: Conditions) -> io::Result<Link> {
+        // the host listens on IPv4, so `localhost` has to be 127.0.0.1
+        let addresses: Vec<SocketAddr> = address.to_socket_addrs()?.collect();
+        let peer = addresses
+            .iter()
+            .find(|peer| peer.is_ipv4())
+            .or_else(|| addresses.first())
+            .cloned()
+            .ok_or_else(|| io::Error::new(ErrorKind::NotFound, format!("can't find {}", address)))?;
+        let any = if peer.is_ipv4() { "0.0.0.0:0" } else { "[::]:0" };
+        let socket = UdpSocket::bind(any)?;
+        Link::new(socket, Some(peer), conditions)
+    }
+
+    fn new(socket: UdpSocket, peer: Option<SocketAddr>, conditions: Conditions) -> io::Result<Link> {
+        socket.set_nonblocking(true)?;
+        Ok(Link {
+            socket,
+            peer,
+            heard: None,
+            conditions,
+            delayed: VecDeque::new(),
+        })
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.socket.local_addr()
+    }
+
+    /// Whether the peer has been heard from.
+    pub fn is_connected(&self) -> bool {
+        self.heard.is_some()
+    }
+
+    /// Whether the peer went quiet after being heard from.
+    pub fn timed_out(&self) -> bool {
+        self.heard.is_some_and(|heard| heard.elapsed() > TIMEOUT)
+    }
+
+    /// Sends `message` to the peer, if there is one yet. Errors are left
+    /// alone, like a lost packet.
+    pub fn send(&mut self, message: &Message) {
+        if self.peer.is_none() {
+            return;
+        }
+        if self.conditions.loss > 0.0 && rand::thread_rng().gen::<f32>() < self.conditions.loss {
+            return;
+        }
+        self.delayed
+            .push_back((Instant::now() + self.conditions.latency, message.encode()));
+        self.flush();
+    }
+
+    /// Sends the packets whose pretend latency is over.
+    pub fn flush(&mut self) {
+        let peer = match self.peer {
+            Some(peer) => peer,
+            None => return,
+        };
+        let now = Instant::now();
+        while self.delayed.front().is_some_and(|&(when, _)| when <= now) {
+            let (_, bytes) = self.delayed.pop_front().unwrap();
+            let _ = self.socket.send_to(&bytes, peer);
+        }
+    }
+
+    /// Everything the peer sent since last time, oldest first.
+    pub fn receive(&mut self) -> Vec<Message> {
+        self.flush();
+        let mut messages = Vec::new();
+        let mut buffer = [0; MAX_PACKET];
+        loop {
+            let (size, from) = match self.socket.recv_from(&mut buffer) {
+                Ok(received) => received,
+                Err(ref e) if e.kind() == ErrorKind::WouldBlock => break,
+                // an earlier packet that couldn't be delivered, try the next
+                Err(ref e) if e.kind() == ErrorKind::ConnectionReset => continue,
+                Err(_) => break,
+            };
+            let message = match Message::decode(&buffer[..size]) {
+                Some(message) => message,
+                None => continue,
+            };
+            match self.peer {
+                Some(peer) if peer != from => continue,
+                Some(_) => {}
+                None if message == Message::Hello => self.peer = Some(from),
+                None => continue,
+            }
+            self.heard = Some(Instant::now());
+            messages.push(message);
+        }
+        messages
+    }
+}
@@ -0,0 +1,88 @@
+//! Settings from the command line.
+
+use std::time::Duration;
+
+use net::{Conditions, MAX_LATENCY};
+
+pub const USAGE: &str = "\
+Usage: pong-ggez [options]
+
+  --host PORT       wait on PORT for a player to join over the network,
+                    you play the left paddle
+  --join ADDRESS    play the right paddle in the game hosted at ADDRESS,
+                    like 192.168.0.2:4000 or localhost:4000
+  --latency MS      hold back every packet sent this many milliseconds,
+                    for testing
+  --loss PERCENT    drop this share of the packets sent, for testing
+  --help            show this message
+
+With no options the start menu picks a game on this machine.";
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Options {
+    /// Port to host a network game on.
+    pub host: Option<u16>,
+    /// Address of the host of the network game to join.
+    pub join: Option<String>,
+    /// Milliseconds added to every packet sent.
+    pub latency: u64,
+    /// Percent of the packets sent that are dropped.
+    pub loss: f32,
+    pub help: bool,
+}
+
+impl Options {
+    /// Reads `args`, the program name left out.
+    pub fn parse_args<I: IntoIterator<Item = String>>(args: I) -> Result<Options, String> {
+        let mut options = Options::default();
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let mut value = |flag: &str| {
+                args.next()
+                    .ok_or_else(|| format!("{} needs a value", flag))
+            };
+            match arg.as_str() {
+                "--host" => options.host = Some(parse_value(&value(&arg)?, &arg)?),
+                "--join" => options.join = Some(value(&arg)?),
+                "--latency" => options.latency = parse_value(&value(&arg)?, &arg)?,
+                "--loss" => options.loss = parse_value(&value(&arg)?, &arg)?,
+                "--help" | "-h" => options.help = true,
+                _ => return Err(format!("unknown option '{}'", arg)),
+            }
+        }
+        Ok(options)
+    }
+
+    /// Checks every value can be played with.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.host.is_some() && self.join.is_some() {
+            return Err(String::from("--host and --join can't be used together"));
+        }
+        if self.host.is_none() && self.join.is_none() && (self.latency > 0 || self.loss > 0.0) {
+            return Err(String::from("--latency and --loss need --host or --join"));
+        }
+        if self.latency > MAX_LATENCY {
+            return Err(format!(
+                "the latency must be at most {} milliseconds, not {}",
+                MAX_LATENCY, self.latency
+            ));
+        }
+        if !(0.0..=100.0).contains(&self.loss) {
+            return Err(format!("the loss must be between 0 and 100 percent, not {}", self.loss));
+        }
+        Ok(())
+    }
+
+    pub fn conditions(&self) -> Conditions {
+        Conditions {
+            latency: Duration::from_millis(self.latency),
+            loss: self.loss / 100.0,
+        }
+    }
+}
+
+fn parse_value<T: ::std::str::FromStr>(value: &str, what: &str) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("{}: '{}' is not a valid value", what, value))
+}
@@ -0,0 +1,49 @@
+//! Where a point is at.
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum PlayerSide {
+    Left,
+    Right,
+}
+
+impl PlayerSide {
+    pub fn other(&self) -> PlayerSide {
+        match *self {
+            PlayerSide::Left => PlayerSide::Right,
+            PlayerSide::Right => PlayerSide::Left,
+        }
+    }
+}
+
+/// After a goal the player who conceded it gets the serve: the ball is
+/// launched toward them once the countdown is over.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Serve {
+    /// "Point!" shows on the side that scored.
+    Point { scorer: PlayerSide, time_left: f32 },
+    /// The ball waits in the middle.
+    Countdown { receiver: PlayerSide, time_left: f32 },
+    /// The ball is in play.
+    Rally,
+}
+
+/// Who scores with the ball's center at `x` on a court `court_w` wide:
+/// the player on the other side from where it went out. `None` while it
+/// is on the court.
+pub fn scorer(x: f32, court_w: f32) -> Option<PlayerSide> {
+    if x < 0.0 {
+        Some(PlayerSide::Right)
+    } else if x > court_w {
+        Some(PlayerSide::Left)
+    } else {
+        None
+    }
+}
+
+/// Gives `scorer` a point, the left player's score coming first.
+pub fn add_point(score: &mut (u32, u32), scorer: PlayerSide) {
+    match scorer {
+        PlayerSide::Left => score.0 += 1,
+        PlayerSide::Right => score.1 += 1,
+    }
+}
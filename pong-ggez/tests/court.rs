@@ -0,0 +1,166 @@
+extern crate pong_ggez;
+
+use pong_ggez::court::{self, Ball, Rect, BALL_ACC, MAX_BALL_SPEED};
+use pong_ggez::cpu;
+use pong_ggez::serve::{self, PlayerSide};
+
+const COURT_H: f32 = 700.0;
+const STEP: f32 = 1.0 / 120.0;
+
+fn ball_at(x: f32, y: f32, vel_x: f32, vel_y: f32) -> Ball {
+    let mut ball = Ball::new(x, y);
+    ball.vel_x = vel_x;
+    ball.vel_y = vel_y;
+    ball
+}
+
+fn close(a: f32, b: f32) -> bool {
+    (a - b).abs() < 1e-3
+}
+
+/// Whether the ball overlaps `rect` anywhere.
+fn overlaps(ball: &Ball, rect: &Rect) -> bool {
+    let near_x = ball.x.max(rect.x).min(rect.x + rect.w);
+    let near_y = ball.y.max(rect.y).min(rect.y + rect.h);
+    (ball.x - near_x).hypot(ball.y - near_y) < ball.radius - 1e-3
+}
+
+#[test]
+fn fast_ball_does_not_tunnel() {
+    // 250 pixels a step, far more than the paddle is wide
+    let paddle = Rect::new(860.0, 286.0, 32.0, 128.0);
+    let mut ball = ball_at(700.0, 350.0, 30_000.0, 0.0);
+
+    let hits = ball.update(STEP, COURT_H, &[paddle]);
+    assert_eq!(hits, vec![0]);
+    assert!(ball.vel_x < 0.0);
+    assert!(ball.x + ball.radius <= paddle.x + 1e-3);
+    assert!(!overlaps(&ball, &paddle));
+}
+
+#[test]
+fn face_hit_speeds_up_and_spins() {
+    let paddle = Rect::new(40.0, 300.0, 32.0, 128.0);
+    // straight at the middle of the face, no spin
+    let mut ball = ball_at(84.0, 364.0, -600.0, 0.0);
+    assert_eq!(ball.update(STEP, COURT_H, &[paddle]), vec![0]);
+    assert!(close(ball.vel_x, 600.0 + BALL_ACC));
+    assert!(close(ball.vel_y, 0.0));
+    // the bounce point is right against the face, the rest of the step
+    // is spent going back
+    let back = (600.0 + BALL_ACC) * (STEP - 2.0 / 600.0);
+    assert!(close(ball.x, 72.0 + ball.radius + back));
+
+    // lower down the face sends it down
+    let mut ball = ball_at(84.0, 410.0, -600.0, 0.0);
+    ball.update(STEP, COURT_H, &[paddle]);
+    assert!(ball.vel_x > 0.0);
+    assert!(ball.vel_y > 0.0);
+}
+
+#[test]
+fn edge_hit_bounces_up_or_down() {
+    let paddle = Rect::new(400.0, 300.0, 32.0, 128.0);
+    // falling onto the top edge keeps going sideways the same way
+    let mut ball = ball_at(416.0, 285.0, 30.0, 1200.0);
+    assert_eq!(ball.update(STEP, COURT_H, &[paddle]), vec![0]);
+    assert!(ball.vel_y < 0.0);
+    assert!(ball.vel_x > 0.0);
+    assert!(!overlaps(&ball, &paddle));
+
+    let mut ball = ball_at(416.0, 443.0, -30.0, -1200.0);
+    assert_eq!(ball.update(STEP, COURT_H, &[paddle]), vec![0]);
+    assert!(ball.vel_y > 0.0);
+    assert!(ball.vel_x < 0.0);
+}
+
+#[test]
+fn corner_hit_bounces_off_the_round() {
+    let paddle = Rect::new(400.0, 300.0, 32.0, 128.0);
+    let corner = (400.0, 300.0);
+    // aimed straight at the top left corner from up and to the left
+    let offset = 20.0 / 2f32.sqrt();
+    let from = (corner.0 - offset, corner.1 - offset);
+    let hit = court::sweep(from, (offset, offset), 10.0, &paddle).unwrap();
+    let diagonal = -1.0 / 2f32.sqrt();
+    assert!(close(hit.normal.0, diagonal) && close(hit.normal.1, diagonal));
+    assert!(close(hit.time, 0.5));
+
+    // and comes straight back
+    let (vel_x, vel_y) = court::reflect((600.0, 600.0), hit.normal);
+    assert!(close(vel_x, -600.0) && close(vel_y, -600.0));
+
+    // just past the corner it misses
+    let from = (corner.0 - 20.0, corner.1 - 40.0);
+    assert_eq!(court::sweep(from, (0.0, 60.0), 10.0, &paddle), None);
+}
+
+#[test]
+fn ball_leaving_a_paddle_is_not_hit_again() {
+    let paddle = Rect::new(40.0, 300.0, 32.0, 128.0);
+    // just touching the face and heading away
+    let mut ball = ball_at(82.0, 364.0, 600.0, 0.0);
+    assert!(ball.update(STEP, COURT_H, &[paddle]).is_empty());
+    assert!(ball.vel_x > 0.0);
+
+    // a paddle that moved over the ball pushes it out
+    let mut ball = ball_at(78.0, 364.0, 600.0, 0.0);
+    assert!(ball.update(STEP, COURT_H, &[paddle]).is_empty());
+    assert!(!overlaps(&ball, &paddle));
+}
+
+#[test]
+fn bounces_off_top_and_bottom() {
+    let mut ball = ball_at(450.0, 12.0, 100.0, -600.0);
+    ball.update(STEP, COURT_H, &[]);
+    assert!(ball.vel_y > 0.0);
+    assert!(ball.y >= ball.radius);
+
+    let mut ball = ball_at(450.0, COURT_H - 12.0, 100.0, 600.0);
+    ball.update(STEP, COURT_H, &[]);
+    assert!(ball.vel_y < 0.0);
+    assert!(ball.y <= COURT_H - ball.radius);
+}
+
+#[test]
+fn rally_speed_is_capped() {
+    let left = Rect::new(8.0, 286.0, 32.0, 128.0);
+    let right = Rect::new(860.0, 286.0, 32.0, 128.0);
+    let mut ball = ball_at(450.0, 350.0, 1400.0, 0.0);
+    let mut hits = 0;
+    for _ in 0..120 * 30 {
+        hits += ball.update(STEP, COURT_H, &[left, right]).len();
+        assert!(ball.speed() <= MAX_BALL_SPEED + 1e-2);
+        assert!(!overlaps(&ball, &left) && !overlaps(&ball, &right));
+        assert!(ball.x > 0.0 && ball.x < 900.0);
+    }
+    assert!(hits > 30);
+    assert!(close(ball.speed(), MAX_BALL_SPEED));
+}
+
+#[test]
+fn cpu_predicts_wall_bounces() {
+    // two court heights down on the way: back where it started
+    let ball = ball_at(100.0, 200.0, 100.0, 2.0 * (COURT_H - 20.0));
+    assert!(close(cpu::predict_y(&ball, 200.0, COURT_H), 200.0));
+    // half way down it has bounced off the bottom
+    let ball = ball_at(100.0, 600.0, 100.0, 180.0);
+    assert!(close(cpu::predict_y(&ball, 200.0, COURT_H), 600.0));
+}
+
+#[test]
+fn goals_score_for_the_other_side() {
+    let mut score = (0, 0);
+    // out on the left is a point for the right player
+    let scorer = serve::scorer(-5.0, 900.0).unwrap();
+    assert_eq!(scorer, PlayerSide::Right);
+    serve::add_point(&mut score, scorer);
+    assert_eq!(score, (0, 1));
+
+    let scorer = serve::scorer(905.0, 900.0).unwrap();
+    assert_eq!(scorer, PlayerSide::Left);
+    serve::add_point(&mut score, scorer);
+    assert_eq!(score, (1, 1));
+
+    assert_eq!(serve::scorer(450.0, 900.0), None);
+}
@@ -0,0 +1,347 @@
+extern crate pong_ggez;
+
+use std::thread;
+use std::time::{Duration, Instant};
+
+use pong_ggez::cpu::Move;
+use pong_ggez::net::{Conditions, Interpolation, Link, Message, PaddleMotion, Prediction, Snapshot,
+                     INTERPOLATION_DELAY, MAX_INPUTS, MAX_PENDING};
+use pong_ggez::options::Options;
+use pong_ggez::serve::{PlayerSide, Serve};
+
+const STEP: f32 = 1.0 / 120.0;
+const MOTION: PaddleMotion = PaddleMotion {
+    speed: 210.0,
+    max_y: 572.0,
+};
+
+fn snapshot(tick: u32) -> Snapshot {
+    Snapshot {
+        tick,
+        ack: 0,
+        ball: (450.0, 350.0),
+        ball_vel: (0.0, 0.0),
+        left_y: 300.0,
+        right_y: 300.0,
+        score: (0, 0),
+        hits: 0,
+        serve: Serve::Rally,
+    }
+}
+
+fn args(line: &str) -> Vec<String> {
+    line.split_whitespace().map(String::from).collect()
+}
+
+#[test]
+fn messages_round_trip() {
+    let state = Snapshot {
+        tick: 12_345,
+        ack: 678,
+        ball: (123.5, -4.25),
+        ball_vel: (-987.0, 65.5),
+        left_y: 0.0,
+        right_y: 572.0,
+        score: (11, 3),
+        hits: 42,
+        serve: Serve::Countdown {
+            receiver: PlayerSide::Right,
+            time_left: 2.5,
+        },
+    };
+    let messages = vec![
+        Message::Hello,
+        Message::Bye,
+        Message::Input {
+            first: 7,
+            moves: vec![Move::Up, Move::Stop, Move::Down],
+        },
+        Message::Input {
+            first: 0,
+            moves: Vec::new(),
+        },
+        Message::State(state),
+        Message::State(Snapshot {
+            serve: Serve::Point {
+                scorer: PlayerSide::Left,
+                time_left: 0.75,
+            },
+            ..state
+        }),
+        Message::State(snapshot(0)),
+    ];
+    for message in messages {
+        let bytes = message.encode();
+        assert_eq!(Message::decode(&bytes), Some(message.clone()));
+        // cut short or with anything left over it isn't a packet
+        assert_eq!(Message::decode(&bytes[..bytes.len() - 1]), None);
+        let mut longer = bytes.clone();
+        longer.push(0);
+        assert_eq!(Message::decode(&longer), None);
+    }
+
+    // the layout is fixed, the other end may be another build
+    assert_eq!(Message::Hello.encode(), b"PG\x01\x00".to_vec());
+    let input = Message::Input {
+        first: 0x0102_0304,
+        moves: vec![Move::Down, Move::Up],
+    };
+    assert_eq!(input.encode(), b"PG\x01\x01\x04\x03\x02\x01\x02\x02\x01".to_vec());
+    assert_eq!(Message::State(state).encode().len(), 4 + 4 * 12 + 2);
+}
+
+#[test]
+fn garbage_is_not_a_message() {
+    for bytes in &[
+        &b""[..],
+        &b"PG"[..],
+        &b"XX\x01\x00"[..],
+        &b"PG\x02\x00"[..],
+        &b"PG\x01\x09"[..],
+        // a move that doesn't exist
+        &b"PG\x01\x01\x00\x00\x00\x00\x01\x07"[..],
+    ] {
+        assert_eq!(Message::decode(bytes), None, "{:?}", bytes);
+    }
+
+    // a serve that doesn't exist
+    let mut bytes = Message::State(snapshot(1)).encode();
+    let kind = bytes.len() - 6;
+    bytes[kind] = 9;
+    assert_eq!(Message::decode(&bytes), None);
+}
+
+#[test]
+fn inputs_are_sent_until_applied() {
+    let mut prediction = Prediction::new(MOTION);
+    let mut y = 300.0;
+    for _ in 0..3 {
+        y = prediction.step(y, Move::Down, STEP);
+    }
+    assert_eq!(
+        prediction.input(),
+        Message::Input {
+            first: 0,
+            moves: vec![Move::Down; 3],
+        }
+    );
+
+    // the host applied the first two: only the last is sent again
+    prediction.reconcile(2, 300.0, STEP);
+    y = prediction.step(y, Move::Up, STEP);
+    assert_eq!(
+        prediction.input(),
+        Message::Input {
+            first: 2,
+            moves: vec![Move::Down, Move::Up],
+        }
+    );
+    assert!(y > 300.0);
+
+    // with the host gone quiet only the newest go out
+    for _ in 0..100 {
+        prediction.step(y, Move::Stop, STEP);
+    }
+    match prediction.input() {
+        Message::Input { first, moves } => {
+            assert_eq!(moves.len(), MAX_INPUTS);
+            assert_eq!(first, 104 - MAX_INPUTS as u32);
+        }
+        other => panic!("expected an input, got {:?}", other),
+    }
+}
+
+#[test]
+fn prediction_matches_the_host() {
+    let mut prediction = Prediction::new(MOTION);
+    let moves = [Move::Down, Move::Down, Move::Stop, Move::Up, Move::Down, Move::Down];
+
+    // the client moves ahead, the host applies the same moves later
+    let mut client_y = 300.0;
+    for &step in &moves {
+        client_y = prediction.step(client_y, step, STEP);
+    }
+    let mut host_y = 300.0;
+    for &step in &moves[..4] {
+        host_y = MOTION.apply(host_y, step, STEP);
+    }
+    let reconciled = prediction.reconcile(4, host_y, STEP);
+    assert!((reconciled - client_y).abs() < 1e-4);
+
+    // where the host disagrees it wins, the moves it hasn't seen on top
+    let reconciled = prediction.reconcile(4, 100.0, STEP);
+    let expected = MOTION.apply(MOTION.apply(100.0, Move::Down, STEP), Move::Down, STEP);
+    assert!((reconciled - expected).abs() < 1e-4);
+
+    // paddles stay on the court
+    assert_eq!(MOTION.apply(1.0, Move::Up, 1.0), 0.0);
+    assert_eq!(MOTION.apply(570.0, Move::Down, 1.0), MOTION.max_y);
+}
+
+#[test]
+fn prediction_outlasts_the_pending_moves() {
+    let mut prediction = Prediction::new(MOTION);
+    let mut client_y = 300.0;
+    for number in 0..MAX_PENDING + 100 {
+        let step = if number < 10 { Move::Down } else { Move::Stop };
+        client_y = prediction.step(client_y, step, STEP);
+    }
+    assert!(client_y > 300.0);
+
+    // the first moves are gone before the host applied them, so the paddle
+    // can't be worked out again from where the host has it
+    assert_eq!(prediction.reconcile(0, 300.0, STEP), client_y);
+    assert_eq!(prediction.reconcile(50, client_y, STEP), client_y);
+
+    // once the host catches up it wins again
+    let acked = (MAX_PENDING + 100) as u32;
+    assert_eq!(prediction.reconcile(acked, 250.0, STEP), 250.0);
+    assert_eq!(prediction.step(250.0, Move::Stop, STEP), 250.0);
+}
+
+#[test]
+fn interpolation_shows_the_past() {
+    let mut view = Interpolation::new();
+    assert_eq!(view.sample(), None);
+
+    for tick in 0..20 {
+        let state = Snapshot {
+            ball: (tick as f32 * 10.0, 350.0),
+            left_y: tick as f32,
+            ..snapshot(tick)
+        };
+        assert!(view.push(state));
+        view.advance(1.0);
+    }
+    // late or repeated states are dropped
+    assert!(!view.push(snapshot(5)));
+    assert!(!view.push(snapshot(19)));
+
+    let shown = view.sample().unwrap();
+    let behind = 19.0 - shown.ball.0 / 10.0;
+    assert!(behind > 1.0 && behind <= INTERPOLATION_DELAY + 1.0, "{} behind", behind);
+    assert!((shown.left_y - shown.ball.0 / 10.0).abs() < 1e-3);
+
+    // half way between two states
+    let mut view = Interpolation::new();
+    view.push(snapshot(100));
+    view.push(Snapshot {
+        ball: (460.0, 360.0),
+        left_y: 310.0,
+        ..snapshot(102)
+    });
+    view.advance(INTERPOLATION_DELAY + 1.0);
+    let shown = view.sample().unwrap();
+    assert!(shown.tick == 100 && shown.ball.0 > 450.0 && shown.ball.0 < 460.0);
+
+    // no sliding across a serve
+    let mut view = Interpolation::new();
+    view.push(Snapshot {
+        ball: (880.0, 100.0),
+        ..snapshot(100)
+    });
+    view.push(Snapshot {
+        serve: Serve::Point {
+            scorer: PlayerSide::Left,
+            time_left: 1.0,
+        },
+        ..snapshot(102)
+    });
+    view.advance(INTERPOLATION_DELAY + 1.0);
+    assert_eq!(view.sample().unwrap().ball, (880.0, 100.0));
+}
+
+#[test]
+fn link_over_localhost() {
+    let mut host = Link::host(0, Conditions::default()).unwrap();
+    let port = host.local_addr().unwrap().port();
+    let mut client = Link::join(&format!("127.0.0.1:{}", port), Conditions::default()).unwrap();
+
+    // the host has nobody to talk to until the client says hello
+    host.send(&Message::Bye);
+    assert!(!host.is_connected());
+    client.send(&Message::Hello);
+    let received = wait_for(&mut host);
+    assert_eq!(received, vec![Message::Hello]);
+    assert!(host.is_connected() && !host.timed_out());
+
+    host.send(&Message::State(snapshot(1)));
+    assert_eq!(wait_for(&mut client), vec![Message::State(snapshot(1))]);
+
+    // a stranger is ignored once the game is on
+    let mut stranger = Link::join(&format!("127.0.0.1:{}", port), Conditions::default()).unwrap();
+    stranger.send(&Message::Hello);
+    client.send(&Message::Bye);
+    assert_eq!(wait_for(&mut host), vec![Message::Bye]);
+}
+
+#[test]
+fn pretend_latency_and_loss() {
+    let mut host = Link::host(0, Conditions::default()).unwrap();
+    let port = host.local_addr().unwrap().port();
+    let address = format!("127.0.0.1:{}", port);
+
+    let lossy = Conditions {
+        latency: Duration::from_millis(0),
+        loss: 1.0,
+    };
+    let mut client = Link::join(&address, lossy).unwrap();
+    for _ in 0..10 {
+        client.send(&Message::Hello);
+    }
+    thread::sleep(Duration::from_millis(50));
+    assert!(host.receive().is_empty());
+
+    let slow = Conditions {
+        latency: Duration::from_millis(150),
+        loss: 0.0,
+    };
+    let mut client = Link::join(&address, slow).unwrap();
+    let sent = Instant::now();
+    client.send(&Message::Hello);
+    thread::sleep(Duration::from_millis(50));
+    assert!(host.receive().is_empty());
+    // held back until the client gets round to flushing it
+    while host.receive().is_empty() {
+        assert!(sent.elapsed() < Duration::from_secs(2));
+        client.flush();
+        thread::sleep(Duration::from_millis(5));
+    }
+    assert!(sent.elapsed() >= Duration::from_millis(150));
+}
+
+/// Polls `link` until something comes, up to two seconds.
+fn wait_for(link: &mut Link) -> Vec<Message> {
+    let start = Instant::now();
+    loop {
+        let messages = link.receive();
+        if !messages.is_empty() || start.elapsed() > Duration::from_secs(2) {
+            return messages;
+        }
+        thread::sleep(Duration::from_millis(5));
+    }
+}
+
+#[test]
+fn network_options() {
+    let options = Options::parse_args(args("--join localhost:4000 --latency 80 --loss 5")).unwrap();
+    assert_eq!(options.join, Some(String::from("localhost:4000")));
+    assert!(options.validate().is_ok());
+    let conditions = options.conditions();
+    assert_eq!(conditions.latency, Duration::from_millis(80));
+    assert!((conditions.loss - 0.05).abs() < 1e-6);
+
+    assert_eq!(Options::parse_args(args("--host 4000")).unwrap().host, Some(4000));
+    assert!(Options::parse_args(args("--host")).is_err());
+    assert!(Options::parse_args(args("--host port")).is_err());
+    assert!(Options::parse_args(args("--lag 5")).is_err());
+    for bad in &[
+        "--host 4000 --join localhost:4000",
+        "--latency 50",
+        "--host 4000 --latency 99999",
+        "--join localhost:4000 --loss 150",
+    ] {
+        let options = Options::parse_args(args(bad)).unwrap();
+        assert!(options.validate().is_err(), "{} should not be valid", bad);
+    }
+}
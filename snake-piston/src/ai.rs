@@ -0,0 +1,217 @@
+//! Computer players.
+//!
+//! A `Controller` picks the turn a snake makes on each step, so it can take
+//! the place of a player's keys both in the window and in headless games.
+//! Controllers never ask a snake to turn back onto its own neck.
+
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use game::Game;
+use snake::Direction;
+
+pub trait Controller {
+    /// Turn for `player` on the next step, `None` to keep going straight.
+    fn next_direction(&mut self, game: &Game, player: usize) -> Option<Direction>;
+}
+
+/// Directions the snake of `player` may take without reversing.
+fn allowed(game: &Game, player: usize) -> Vec<Direction> {
+    let back = game.snake(player).head_direction().opposite();
+    Direction::all().iter().cloned().filter(|&d| d != back).collect()
+}
+
+/// Whether the head of `player` can move onto `cell` next step. Its own
+/// tail is fine, it moves out of the way at the same time.
+fn is_safe(game: &Game, player: usize, cell: (i32, i32)) -> bool {
+    let snake = game.snake(player);
+    game.is_free(cell.0, cell.1) || (snake.len() > 2 && snake.tail_position() == cell)
+}
+
+/// Number of free cells reachable from `start`, giving up after `limit`.
+fn reachable(game: &Game, start: (i32, i32), limit: usize) -> usize {
+    let mut seen = HashSet::new();
+    let mut queue = VecDeque::new();
+    seen.insert(start);
+    queue.push_back(start);
+
+    while let Some((x, y)) = queue.pop_front() {
+        if seen.len() >= limit {
+            break;
+        }
+        for &dir in &Direction::all() {
+            let next = game.next_cell(x, y, dir);
+            if game.is_free(next.0, next.1) && seen.insert(next) {
+                queue.push_back(next);
+            }
+        }
+    }
+    seen.len()
+}
+
+/// Heads for the nearest food that makes it grow, along the shortest free
+/// path.
+///
+/// When the food can't be reached it takes the move leaving the most room,
+/// which keeps it alive until a path opens up again.
+#[derive(Debug, Default, Clone)]
+pub struct Greedy;
+
+impl Greedy {
+    pub fn new() -> Greedy {
+        Greedy
+    }
+
+    fn path_to_food(&self, game: &Game, player: usize) -> Option<Direction> {
+        let food: HashSet<(i32, i32)> = game.foods()
+            .iter()
+            .filter(|f| f.kind.grows())
+            .map(|f| (f.x, f.y))
+            .collect();
+        if food.is_empty() {
+            return None;
+        }
+        let (head_x, head_y) = game.snake(player).head_position();
+
+        let mut first_step: HashMap<(i32, i32), Direction> = HashMap::new();
+        let mut queue = VecDeque::new();
+        for dir in allowed(game, player) {
+            let next = game.next_cell(head_x, head_y, dir);
+            if is_safe(game, player, next) && !first_step.contains_key(&next) {
+                first_step.insert(next, dir);
+                queue.push_back(next);
+            }
+        }
+
+        while let Some((x, y)) = queue.pop_front() {
+            let dir = first_step[&(x, y)];
+            if food.contains(&(x, y)) {
+                return Some(dir);
+            }
+            for &d in &Direction::all() {
+                let next = game.next_cell(x, y, d);
+                if game.is_free(next.0, next.1) && !first_step.contains_key(&next) {
+                    first_step.insert(next, dir);
+                    queue.push_back(next);
+                }
+            }
+        }
+        None
+    }
+
+    fn roomiest(&self, game: &Game, player: usize) -> Option<Direction> {
+        let (head_x, head_y) = game.snake(player).head_position();
+        let limit = (game.width() * game.height()) as usize;
+
+        allowed(game, player)
+            .into_iter()
+            .map(|dir| (dir, game.next_cell(head_x, head_y, dir)))
+            .filter(|&(_, cell)| is_safe(game, player, cell))
+            .max_by_key(|&(_, cell)| reachable(game, cell, limit))
+            .map(|(dir, _)| dir)
+    }
+}
+
+impl Controller for Greedy {
+    fn next_direction(&mut self, game: &Game, player: usize) -> Option<Direction> {
+        self.path_to_food(game, player)
+            .or_else(|| self.roomiest(game, player))
+    }
+}
+
+/// Follows a fixed cycle through every cell of the board, so once the snake
+/// is lined up on it, it can never run into itself.
+///
+/// The cycle needs a board without walls and an even number of rows or
+/// columns. Where there is none, or another snake is in the way, it plays
+/// like `Greedy`.
+#[derive(Debug, Clone, Default)]
+pub struct Hamiltonian {
+    /// Position of each cell along the cycle.
+    order: HashMap<(i32, i32), usize>,
+    /// Board the cycle was built for, rebuilt when it changes.
+    board: Option<(i32, i32, usize)>,
+    fallback: Greedy,
+}
+
+impl Hamiltonian {
+    pub fn new() -> Hamiltonian {
+        Hamiltonian::default()
+    }
+
+    fn build(&mut self, game: &Game) {
+        let board = (game.width(), game.height(), game.level_number());
+        if self.board == Some(board) {
+            return;
+        }
+        self.board = Some(board);
+        self.order.clear();
+
+        if !game.level().walls.is_empty() {
+            return;
+        }
+
+        let bounds = game.bounds();
+        let columns = bounds.right - bounds.left + 1;
+        let rows = bounds.bottom - bounds.top + 1;
+        let cycle = if rows % 2 == 0 {
+            cycle(columns, rows)
+        } else if columns % 2 == 0 {
+            cycle(rows, columns).into_iter().map(|(x, y)| (y, x)).collect()
+        } else {
+            Vec::new()
+        };
+
+        for (i, (x, y)) in cycle.into_iter().enumerate() {
+            self.order.insert((bounds.left + x, bounds.top + y), i);
+        }
+    }
+}
+
+/// Cells of a cycle through a `columns` by `rows` board, rows being even.
+///
+/// The top row is walked to the right, the other rows zig-zag down
+/// leaving out the first column, which leads back up to the start.
+fn cycle(columns: i32, rows: i32) -> Vec<(i32, i32)> {
+    let mut cells = Vec::new();
+    if columns < 2 || rows < 2 {
+        return cells;
+    }
+
+    for x in 0..columns {
+        cells.push((x, 0));
+    }
+    for y in 1..rows {
+        if y % 2 == 1 {
+            for x in (1..columns).rev() {
+                cells.push((x, y));
+            }
+        } else {
+            for x in 1..columns {
+                cells.push((x, y));
+            }
+        }
+    }
+    for y in (1..rows).rev() {
+        cells.push((0, y));
+    }
+    cells
+}
+
+impl Controller for Hamiltonian {
+    fn next_direction(&mut self, game: &Game, player: usize) -> Option<Direction> {
+        self.build(game);
+
+        let (head_x, head_y) = game.snake(player).head_position();
+        if let Some(&index) = self.order.get(&(head_x, head_y)) {
+            let target = (index + 1) % self.order.len();
+            for dir in allowed(game, player) {
+                let (x, y) = game.next_cell(head_x, head_y, dir);
+                if self.order.get(&(x, y)) == Some(&target) && is_safe(game, player, (x, y)) {
+                    return Some(dir);
+                }
+            }
+        }
+
+        self.fallback.next_direction(game, player)
+    }
+}
@@ -0,0 +1,1006 @@
+use piston_window::*;
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use rand::{thread_rng, Rng};
+
+use rusty_snake::ai::{Controller, Greedy, Hamiltonian};
+use rusty_snake::daily::{self, Challenge, DailyResult};
+use rusty_snake::food::Effect;
+use rusty_snake::game::{Game, GameConfig};
+use rusty_snake::input::InputQueue;
+use rusty_snake::level::Level;
+use rusty_snake::options::Options;
+use rusty_snake::replay::{Playback, Replay};
+use rusty_snake::rewind::Rewind;
+use rusty_snake::snake::{Direction, Snake};
+use rusty_snake::storage;
+use rusty_snake::theme::Theme;
+
+use draw::{board_context, draw_block, draw_rectangle, draw_sprite, draw_text, fill, fill_round};
+use skin::Themes;
+
+const HUD_FONT_SIZE: u32 = 16;
+const TITLE_FONT_SIZE: u32 = 40;
+const SCREEN_FONT_SIZE: u32 = 20;
+
+/// Blocks of the bar showing an effect that just started.
+const EFFECT_BAR_BLOCKS: u32 = 8;
+/// Items about to go away blink for their last steps.
+const BLINK_STEPS: u32 = 10;
+
+const MIN_MOVING_PERIOD: f64 = 0.06;
+/// Each block the snake grows makes it this much faster.
+const SPEEDUP: f64 = 0.97;
+const SLOW_FACTOR: f64 = 1.6;
+const FAST_FACTOR: f64 = 0.6;
+/// Keys pressed this soon after a game ends don't restart it yet, they
+/// were most likely meant for the snake.
+const RESTART_DELAY: f64 = 0.5;
+/// A replay waits this long on a finished game before going on.
+const RESTART_TIME: f64 = 3.0;
+/// Playback speeds cycled with F.
+const REPLAY_SPEEDS: [f64; 4] = [1.0, 2.0, 4.0, 8.0];
+/// Steps practice mode can take back.
+const REWIND_STEPS: usize = 600;
+/// Seconds between two steps taken back while Backspace is held.
+const REWIND_PERIOD: f64 = 0.05;
+/// Largest window in pixels. Bigger boards scroll along with the first
+/// snake, with a minimap of the whole board.
+const MAX_WINDOW: [f64; 2] = [1280.0, 800.0];
+/// Pixels the longer side of the minimap takes.
+const MINIMAP_SIZE: f64 = 160.0;
+/// Pixels between the minimap and the window edges.
+const MINIMAP_MARGIN: f64 = 10.0;
+
+/// Piston frontend: turns key presses and frame time into `Game` steps.
+pub struct App {
+    game: Game,
+    /// Turns typed by each player, made one per tick.
+    inputs: Vec<InputQueue>,
+    /// Computer player steering each snake, `None` for a human.
+    controllers: Vec<Option<Box<dyn Controller>>>,
+    /// Strategy of the first player's autopilot, cycled with Tab.
+    autopilot: Autopilot,
+    /// Best single player score, saved between runs.
+    best: u32,
+    waiting_time: f64,
+    /// Everything played so far, saved whenever a game ends.
+    recording: Replay,
+    /// File name of the recording, after the time the window opened.
+    recording_name: String,
+    /// Set when watching a replay instead of playing.
+    playback: Option<Playback>,
+    screen: Screen,
+    /// The score that ended the last game beat the best one.
+    new_best: bool,
+    /// Index into `REPLAY_SPEEDS`.
+    replay_speed: usize,
+    /// Seconds between two steps when the game starts.
+    start_period: f64,
+    /// Pixels per board cell.
+    cell_size: f64,
+    /// Every theme, switched between with C.
+    themes: Themes,
+    /// Snakes glide between cells instead of jumping a cell per step.
+    smooth: bool,
+    /// Steps to take back in practice mode, `None` otherwise.
+    rewind: Option<Rewind>,
+    /// Backspace is held down in practice mode.
+    rewinding: bool,
+    /// The challenge being played in daily mode, `None` otherwise.
+    daily: Option<Challenge>,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum Screen {
+    /// Waiting for a key before the first game.
+    Title,
+    Playing,
+    /// Stopped with P or Space until pressed again.
+    Paused,
+    /// Showing how the game or round went until a key is pressed.
+    GameOver,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum Autopilot {
+    Off,
+    Greedy,
+    Hamiltonian,
+}
+
+impl App {
+    /// Plays `levels` in order, or the classic empty board when there are
+    /// none. The speed and cell size come from `options`, the themes from
+    /// `set_themes`.
+    pub fn new(config: GameConfig, levels: Vec<Level>, options: &Options) -> App {
+        let recording = Replay::new(config, levels, thread_rng().gen());
+        let started = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        let game = recording.game();
+
+        App {
+            inputs: vec![InputQueue::new(); game.players()],
+            controllers: (0..game.players()).map(|_| None).collect(),
+            autopilot: Autopilot::Off,
+            best: storage::load_best_score(),
+            game,
+            waiting_time: 0.0,
+            recording,
+            recording_name: format!("{}.replay", started),
+            playback: None,
+            screen: Screen::Title,
+            new_best: false,
+            replay_speed: 0,
+            start_period: 1.0 / options.speed,
+            cell_size: options.cell_size,
+            themes: Themes::classic(),
+            smooth: options.smooth,
+            rewind: if options.practice { Some(Rewind::new(REWIND_STEPS)) } else { None },
+            rewinding: false,
+            daily: None,
+        }
+    }
+
+    /// Plays `challenge`, each run recorded on its own so it can be
+    /// verified. The best score shown is the best of that day.
+    pub fn daily(challenge: Challenge, options: &Options) -> App {
+        let mut app = App::new(challenge.config(), Vec::new(), options);
+        app.start_period = 1.0 / daily::SPEED;
+        app.rewind = None;
+        app.daily = Some(challenge);
+        app.best = storage::load_daily_results()
+            .iter()
+            .filter(|result| result.date == challenge.date)
+            .map(|result| result.score)
+            .max()
+            .unwrap_or(0);
+        app.new_daily_run();
+        app
+    }
+
+    /// Starts the daily challenge over with an empty recording, the same
+    /// rng and a file name of its own.
+    fn new_daily_run(&mut self) {
+        if let Some(challenge) = self.daily {
+            let started = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0);
+            self.recording = challenge.replay();
+            self.recording_name = format!("daily-{}-{}.replay", challenge.date, started);
+            self.game = self.recording.game();
+        }
+    }
+
+    /// Watches `replay` instead of playing: Space pauses, Right steps while
+    /// paused, F changes the speed and R starts over.
+    pub fn watch(replay: Replay, options: &Options) -> App {
+        let mut app = App::new(replay.config, Vec::new(), options);
+        app.game = replay.game();
+        app.inputs = vec![InputQueue::new(); app.game.players()];
+        app.controllers = (0..app.game.players()).map(|_| None).collect();
+        app.playback = Some(Playback::new(replay));
+        app.rewind = None;
+        app.screen = Screen::Playing;
+        app
+    }
+
+    /// Hands every snake but the first over to the computer.
+    pub fn add_cpu_opponents(&mut self) {
+        for controller in self.controllers.iter_mut().skip(1) {
+            *controller = Some(Box::new(Greedy::new()));
+        }
+    }
+
+    fn toggle_autopilot(&mut self) {
+        self.autopilot = match self.autopilot {
+            Autopilot::Off => Autopilot::Greedy,
+            Autopilot::Greedy => Autopilot::Hamiltonian,
+            Autopilot::Hamiltonian => Autopilot::Off,
+        };
+        self.controllers[0] = match self.autopilot {
+            Autopilot::Off => None,
+            Autopilot::Greedy => Some(Box::new(Greedy::new())),
+            Autopilot::Hamiltonian => Some(Box::new(Hamiltonian::new())),
+        };
+    }
+
+    pub fn key_pressed(&mut self, key: Key) {
+        if key == Key::C {
+            self.themes.next();
+            return;
+        }
+
+        if self.playback.is_some() {
+            self.playback_key(key);
+            return;
+        }
+
+        // the autopilot would play the challenge for you
+        if key == Key::Tab && self.daily.is_none() {
+            self.toggle_autopilot();
+            return;
+        }
+
+        if key == Key::Backspace && self.rewind.is_some() && self.screen != Screen::Title {
+            self.rewinding = true;
+            self.waiting_time = 0.0;
+            self.screen = Screen::Playing;
+            return;
+        }
+
+        let pause_key = key == Key::P || key == Key::Space;
+        match self.screen {
+            Screen::Title => self.screen = Screen::Playing,
+            Screen::Playing if pause_key => self.screen = Screen::Paused,
+            Screen::Playing => self.steer(key),
+            Screen::Paused if pause_key => self.screen = Screen::Playing,
+            Screen::Paused => {}
+            Screen::GameOver if self.waiting_time > RESTART_DELAY => self.restart(),
+            Screen::GameOver => {}
+        }
+    }
+
+    pub fn key_released(&mut self, key: Key) {
+        // play goes on from wherever the rewind got to
+        if key == Key::Backspace && self.rewinding {
+            self.rewinding = false;
+            self.waiting_time = 0.0;
+            for input in &mut self.inputs {
+                input.clear();
+            }
+        }
+    }
+
+    /// Stops the game, for when the window loses focus.
+    pub fn pause(&mut self) {
+        if self.screen == Screen::Playing {
+            self.screen = Screen::Paused;
+        }
+    }
+
+    fn steer(&mut self, key: Key) {
+        let (player, dir) = match key {
+            Key::Up => (0, Direction::Up),
+            Key::Down => (0, Direction::Down),
+            Key::Left => (0, Direction::Left),
+            Key::Right => (0, Direction::Right),
+            Key::W => (1, Direction::Up),
+            Key::S => (1, Direction::Down),
+            Key::A => (1, Direction::Left),
+            Key::D => (1, Direction::Right),
+            _ => return,
+        };
+
+        if player >= self.game.players() || !self.game.is_alive(player) {
+            return;
+        }
+
+        if self.controllers[player].is_some() {
+            return;
+        }
+
+        let heading = self.game.snake(player).head_direction();
+        self.inputs[player].push(dir, heading);
+    }
+
+    fn playback_key(&mut self, key: Key) {
+        match key {
+            Key::Space | Key::P => {
+                self.screen = match self.screen {
+                    Screen::Paused => Screen::Playing,
+                    _ => Screen::Paused,
+                }
+            }
+            Key::Right if self.screen == Screen::Paused => self.play_move(),
+            Key::F => self.replay_speed = (self.replay_speed + 1) % REPLAY_SPEEDS.len(),
+            Key::R => {
+                if let Some(ref mut playback) = self.playback {
+                    self.game = playback.replay().game();
+                    *playback = Playback::new(playback.replay().clone());
+                }
+                self.waiting_time = 0.0;
+            }
+            _ => {}
+        }
+    }
+
+    /// Window size in pixels to fit the board, or as much of it as fits.
+    pub fn window_size(&self) -> [u32; 2] {
+        let (width, height) = self.view_size();
+        [self.pixels(width) as u32, self.pixels(height) as u32]
+    }
+
+    /// Cells across and down the window.
+    fn view_size(&self) -> (i32, i32) {
+        let fit = |cells: i32, pixels: f64| cells.min((pixels / self.cell_size) as i32);
+        (fit(self.game.width(), MAX_WINDOW[0]), fit(self.game.height(), MAX_WINDOW[1]))
+    }
+
+    /// Board cell in the top left corner of the window, keeping the head of
+    /// the first snake as close to the middle as the board edges allow.
+    fn camera(&self) -> (i32, i32) {
+        let (view_width, view_height) = self.view_size();
+        let (head_x, head_y) = self.game.snake(0).head_position();
+        let follow = |head: i32, view: i32, board: i32| (head - view / 2).max(0).min(board - view);
+        (
+            follow(head_x, view_width, self.game.width()),
+            follow(head_y, view_height, self.game.height()),
+        )
+    }
+
+    fn pixels(&self, cells: i32) -> f64 {
+        cells as f64 * self.cell_size
+    }
+
+    pub fn theme(&self) -> &Theme {
+        self.themes.theme()
+    }
+
+    pub fn set_themes(&mut self, themes: Themes) {
+        self.themes = themes;
+    }
+
+    /// Seconds between two steps, shorter as the snakes grow.
+    fn moving_period(&self) -> f64 {
+        let start = self.game.level().length;
+        let longest = (0..self.game.players())
+            .map(|player| self.game.snake(player).len())
+            .max()
+            .unwrap_or(start);
+        let grown = longest.saturating_sub(start) as i32;
+        let fastest = MIN_MOVING_PERIOD.min(self.start_period);
+        let period = (self.start_period * SPEEDUP.powi(grown)).max(fastest);
+
+        let any_has = |effect| (0..self.game.players()).any(|p| self.game.has_effect(p, effect));
+        if any_has(Effect::Slow) {
+            period * SLOW_FACTOR
+        } else if any_has(Effect::Fast) {
+            period * FAST_FACTOR
+        } else {
+            period
+        }
+    }
+
+    pub fn draw(&self, con: &Context, g: &mut G2d, glyphs: &mut Glyphs) {
+        let theme = self.theme();
+        let view = board_context(con, self.cell_size);
+        let (camera_x, camera_y) = self.camera();
+        let board = view.trans(-camera_x as f64, -camera_y as f64);
+        for &(x, y) in &self.game.level().walls {
+            draw_block(theme.border, x, y, &board, g);
+        }
+
+        for player in 0..self.game.players() {
+            // dead arena snakes are food now
+            if self.game.config().arena && !self.game.is_alive(player) {
+                continue;
+            }
+            let mut color = theme.players[player % theme.players.len()];
+            if self.game.has_effect(player, Effect::Ghost) {
+                color[3] = 0.5;
+            }
+            let snake = self.game.snake(player);
+            if self.smooth {
+                let progress = if self.game.is_alive(player) { self.step_progress() } else { 1.0 };
+                self.draw_smooth_snake(snake, color, progress, &board, g);
+            } else {
+                self.draw_snake(snake, color, &board, g);
+            }
+        }
+
+        let food_sprite = &self.themes.skin().food;
+        for food in self.game.foods() {
+            let steps = food.steps_left.unwrap_or(BLINK_STEPS);
+            if steps < BLINK_STEPS && steps % 2 != 0 {
+                continue;
+            }
+            let color = theme.food(food.kind);
+            match *food_sprite {
+                Some(ref texture) => {
+                    draw_sprite(texture, color, (food.x, food.y), Direction::Right, &board, g)
+                }
+                None => draw_block(color, food.x, food.y, &board, g),
+            }
+        }
+
+        let (width, height) = (self.game.width(), self.game.height());
+        if self.game.is_wrapping() {
+            draw_portal_border(theme, width, height, &board, g);
+        } else {
+            draw_rectangle(theme.border, 0, 0, width, 1, &board, g);
+            draw_rectangle(theme.border, 0, height - 1, width, 1, &board, g);
+            draw_rectangle(theme.border, 0, 0, 1, height, &board, g);
+            draw_rectangle(theme.border, width - 1, 0, 1, height, &board, g);
+        }
+
+        let (width, height) = self.view_size();
+        self.draw_effects(&view, g);
+        self.draw_minimap(con, g);
+        self.draw_hud(con, g, glyphs);
+        if self.game.config().arena {
+            self.draw_scoreboard(con, g, glyphs);
+        }
+
+        if self.game.is_game_over() {
+            let winner = match self.match_winner() {
+                Some(w) => Some(w),
+                None if self.game.is_won() && !self.game.config().is_versus() => Some(0),
+                None => None,
+            };
+            let color = winner
+                .map(|w| {
+                    let c = theme.players[w % theme.players.len()];
+                    [c[0], c[1], c[2], theme.game_over[3]]
+                })
+                .unwrap_or(theme.game_over);
+            draw_rectangle(color, 0, 0, width, height, &view, g);
+        }
+
+        match self.screen {
+            Screen::Title => self.draw_screen(&self.title_lines(), con, g, glyphs),
+            Screen::Paused if self.playback.is_none() => {
+                let lines = vec![String::from("Paused"), String::from("Press P or Space to go on")];
+                self.draw_screen(&lines, con, g, glyphs);
+            }
+            Screen::GameOver => self.draw_screen(&self.game_over_lines(), con, g, glyphs),
+            _ => {}
+        }
+    }
+
+    /// Way to go from cell `from` to the cell `to` next to it.
+    fn toward(&self, from: (i32, i32), to: (i32, i32)) -> Option<Direction> {
+        Direction::all()
+            .iter()
+            .cloned()
+            .find(|&dir| self.game.next_cell(from.0, from.1, dir) == to)
+    }
+
+    /// Blocks of `snake`, or the theme's sprites where it has them, each
+    /// facing the block ahead of it.
+    fn draw_snake(&self, snake: &Snake, color: types::Color, con: &Context, g: &mut G2d) {
+        let skin = self.themes.skin();
+        let cells: Vec<(i32, i32)> = snake.blocks().map(|b| (b.x, b.y)).collect();
+        for (i, &cell) in cells.iter().enumerate() {
+            let (sprite, dir) = if i == 0 {
+                (&skin.head, Some(snake.head_direction()))
+            } else if i == cells.len() - 1 {
+                (&skin.tail, self.toward(cell, cells[i - 1]))
+            } else {
+                (&skin.body, self.toward(cell, cells[i - 1]))
+            };
+            match (sprite.as_ref(), dir) {
+                (Some(texture), Some(dir)) => draw_sprite(texture, color, cell, dir, con, g),
+                _ => draw_block(color, cell.0, cell.1, con, g),
+            }
+        }
+    }
+
+    /// How far the game is from its last step to the next one, 0 to 1.
+    fn step_progress(&self) -> f64 {
+        if self.screen != Screen::Playing || self.game.is_game_over() || self.rewinding {
+            return 1.0;
+        }
+        (self.waiting_time / self.moving_period()).min(1.0)
+    }
+
+    /// Draws `snake` `progress` of the way from its last step to where it
+    /// is now: the head slides into its cell while the tail slides out of
+    /// the one it left. Only the drawing lags, the game is still on cells.
+    fn draw_smooth_snake(
+        &self,
+        snake: &Snake,
+        color: types::Color,
+        progress: f64,
+        con: &Context,
+        g: &mut G2d,
+    ) {
+        let sliding = snake.has_moved() && progress < 1.0;
+        let mut cells: Vec<(i32, i32)> = snake.blocks().map(|b| (b.x, b.y)).collect();
+        let body = cells.len();
+        if sliding {
+            cells.extend(snake.left_tail());
+        }
+        let toward = |from, to| self.toward(from, to);
+
+        for i in 0..body {
+            if i == 0 && sliding {
+                let back = snake.head_direction().opposite();
+                draw_cell_part(color, cells[0], back, progress, con, g);
+                continue;
+            }
+            let ahead = if i > 0 { toward(cells[i], cells[i - 1]) } else { None };
+            let behind = cells.get(i + 1).and_then(|&next| toward(cells[i], next));
+            draw_joint(color, cells[i], &[ahead, behind], con, g);
+        }
+
+        if cells.len() > body {
+            if let Some(dir) = toward(cells[body], cells[body - 1]) {
+                draw_cell_part(color, cells[body], dir, 1.0 - progress, con, g);
+            }
+        }
+    }
+
+    /// Score line on the top border.
+    fn draw_hud(&self, con: &Context, g: &mut G2d, glyphs: &mut Glyphs) {
+        let speed = 1.0 / self.moving_period();
+        let line = if let Some(challenge) = self.daily {
+            format!(
+                "Daily {}   Score {}   Length {}   Steps left {}   Best today {}",
+                challenge.date,
+                self.game.score(0),
+                self.game.snake(0).len(),
+                self.game.steps_left().unwrap_or(0),
+                self.best.max(self.game.score(0))
+            )
+        } else if self.game.config().arena {
+            let alive = (0..self.game.players()).filter(|&p| self.game.is_alive(p)).count();
+            format!(
+                "Arena   Score {}   Length {}   Alive {}/{}   Speed {:.1}",
+                self.game.score(0),
+                self.game.snake(0).len(),
+                alive,
+                self.game.players(),
+                speed
+            )
+        } else if self.game.config().is_versus() {
+            format!(
+                "P1 {} ({} won)   P2 {} ({} won)   Level {}   Speed {:.1}",
+                self.game.score(0),
+                self.game.wins()[0],
+                self.game.score(1),
+                self.game.wins()[1],
+                self.game.level_number(),
+                speed
+            )
+        } else {
+            format!(
+                "Score {}   Length {}   Level {}   Best {}   Speed {:.1}",
+                self.game.score(0),
+                self.game.snake(0).len(),
+                self.game.level_number(),
+                self.best.max(self.game.score(0)),
+                speed
+            )
+        };
+        let line = match self.playback {
+            Some(ref playback) => format!(
+                "Replay {}/{} x{}{}   {}",
+                playback.position(),
+                playback.replay().moves.len(),
+                REPLAY_SPEEDS[self.replay_speed],
+                if self.screen == Screen::Paused { " paused" } else { "" },
+                line
+            ),
+            None => line,
+        };
+        let baseline = self.pixels(1) - 6.0;
+        let style = text::Text::new_color(self.theme().hud, HUD_FONT_SIZE);
+        draw_text(style, &line, self.pixels(1), baseline, glyphs, con, g);
+    }
+
+    /// Snakes still alive in the arena, longest first, in the top right
+    /// corner and in their own colors.
+    fn draw_scoreboard(&self, con: &Context, g: &mut G2d, glyphs: &mut Glyphs) {
+        let mut alive: Vec<usize> = (0..self.game.players())
+            .filter(|&p| self.game.is_alive(p))
+            .collect();
+        alive.sort_by_key(|&p| (::std::cmp::Reverse(self.game.snake(p).len()), p));
+
+        let x = self.pixels(self.view_size().0 - 7);
+        let mut y = self.pixels(2);
+        for player in alive {
+            let line = format!("{}  {}", self.player_name(player), self.game.snake(player).len());
+            let color = self.theme().players[player % self.theme().players.len()];
+            let style = text::Text::new_color(color, HUD_FONT_SIZE);
+            draw_text(style, &line, x, y, glyphs, con, g);
+            y += HUD_FONT_SIZE as f64 + 4.0;
+        }
+    }
+
+    /// What the arena screens call `player`.
+    fn player_name(&self, player: usize) -> String {
+        match player {
+            0 => String::from("You"),
+            _ => format!("CPU {}", player),
+        }
+    }
+
+    /// Darkens the board and writes `lines` on it, the first one as a title.
+    fn draw_screen(&self, lines: &[String], con: &Context, g: &mut G2d, glyphs: &mut Glyphs) {
+        let (width, height) = self.view_size();
+        let view = board_context(con, self.cell_size);
+        draw_rectangle(self.theme().screen, 0, 0, width, height, &view, g);
+
+        let x = self.pixels(3);
+        let mut y = self.pixels(height / 3);
+        for (i, line) in lines.iter().enumerate() {
+            let size = if i == 0 { TITLE_FONT_SIZE } else { SCREEN_FONT_SIZE };
+            let style = text::Text::new_color(self.theme().hud, size);
+            draw_text(style, line, x, y, glyphs, con, g);
+            y += self.pixels(2);
+        }
+    }
+
+    fn title_lines(&self) -> Vec<String> {
+        let mut lines = vec![
+            String::from("Snake"),
+            String::from("Arrow keys to steer, P or Space to pause"),
+        ];
+        if self.game.config().arena {
+            lines.push(String::from("Be the last snake alive, the dead ones turn into food"));
+        } else if self.game.config().is_versus() && self.controllers[1].is_none() {
+            lines.push(String::from("Second player steers with WASD"));
+        }
+        if let Some(challenge) = self.daily {
+            lines.push(format!(
+                "Daily challenge {}: score all you can in {} steps",
+                challenge.date,
+                daily::STEPS
+            ));
+        } else {
+            lines.push(String::from("Tab hands your snake to the autopilot"));
+        }
+        if self.rewind.is_some() {
+            lines.push(String::from("Practice: hold Backspace to rewind"));
+        }
+        lines.push(String::from("C changes the colors"));
+        lines.push(String::from("Press any key to start"));
+        lines
+    }
+
+    fn game_over_lines(&self) -> Vec<String> {
+        let mut lines = Vec::new();
+        if self.game.config().arena {
+            lines.push(match self.match_winner() {
+                Some(0) => String::from("You win!"),
+                Some(w) => format!("{} wins", self.player_name(w)),
+                None => String::from("Nobody made it"),
+            });
+            for player in 0..self.game.players() {
+                lines.push(format!(
+                    "{}: {} points, length {}{}",
+                    self.player_name(player),
+                    self.game.score(player),
+                    self.game.snake(player).len(),
+                    if self.game.is_alive(player) { "" } else { ", dead" }
+                ));
+            }
+        } else if self.game.config().is_versus() {
+            lines.push(match (self.match_winner(), self.round_winner()) {
+                (Some(w), _) => format!("Player {} wins the match!", w + 1),
+                (None, Some(w)) => format!("Player {} wins the round", w + 1),
+                (None, None) => String::from("Draw"),
+            });
+            for player in 0..self.game.players() {
+                lines.push(format!(
+                    "Player {}: {} points, {} rounds won",
+                    player + 1,
+                    self.game.score(player),
+                    self.game.wins()[player]
+                ));
+            }
+        } else if let Some(challenge) = self.daily {
+            lines.push(String::from(if self.game.steps_left() == Some(0) {
+                "Time's up"
+            } else {
+                "Game over"
+            }));
+            lines.push(format!("Score {}", self.game.score(0)));
+            lines.push(format!("Daily {}   Seed {:016x}", challenge.date, challenge.seed));
+            lines.push(if self.new_best {
+                String::from("Best today!")
+            } else {
+                format!("Best today {}", self.best)
+            });
+        } else {
+            lines.push(String::from(if self.game.is_won() { "You win!" } else { "Game over" }));
+            lines.push(format!("Score {}", self.game.score(0)));
+            lines.push(format!(
+                "Length {}   Level {}",
+                self.game.snake(0).len(),
+                self.game.level_number()
+            ));
+            lines.push(if self.new_best {
+                String::from("New best score!")
+            } else {
+                format!("Best {}", self.best)
+            });
+        }
+        lines.push(String::from("Press any key to play again"));
+        lines
+    }
+
+    /// Whoever is still alive once a versus round is over.
+    fn round_winner(&self) -> Option<usize> {
+        let alive: Vec<usize> = (0..self.game.players())
+            .filter(|&p| self.game.is_alive(p))
+            .collect();
+        if alive.len() == 1 {
+            Some(alive[0])
+        } else {
+            None
+        }
+    }
+
+    /// A bar per running effect on the bottom row of the window, shrinking
+    /// as it runs out: first player from the left, second player from the
+    /// right. The arena only shows your own.
+    fn draw_effects(&self, con: &Context, g: &mut G2d) {
+        let (width, height) = self.view_size();
+        let players = if self.game.config().arena { 1 } else { self.game.players() };
+        for player in 0..players {
+            let mut x = 1;
+            for active in self.game.effects(player) {
+                let duration = active.effect.duration();
+                let blocks = (active.steps_left * EFFECT_BAR_BLOCKS).div_ceil(duration);
+                let blocks = blocks as i32;
+                let left = match player {
+                    0 => x,
+                    _ => width - 1 - x - blocks,
+                };
+                let color = self.theme().effect(active.effect);
+                draw_rectangle(color, left, height - 1, blocks, 1, con, g);
+                x += EFFECT_BAR_BLOCKS as i32 + 1;
+            }
+        }
+    }
+
+    /// The whole board shrunk into the bottom right corner with the part in
+    /// the window outlined, when the board doesn't fit in the window.
+    fn draw_minimap(&self, con: &Context, g: &mut G2d) {
+        let (width, height) = (self.game.width(), self.game.height());
+        let (view_width, view_height) = self.view_size();
+        if (view_width, view_height) == (width, height) {
+            return;
+        }
+
+        let scale = MINIMAP_SIZE / width.max(height) as f64;
+        let left = self.pixels(view_width) - width as f64 * scale - MINIMAP_MARGIN;
+        let top = self.pixels(view_height) - height as f64 * scale - MINIMAP_MARGIN;
+        let map = con.trans(left, top).zoom(scale);
+        // at least a couple of pixels, or single cells would not show
+        draw_rectangle(self.theme().screen, 0, 0, width, height, &map, g);
+        let dot = (2.0 / scale).max(1.0);
+        let mut mark = |color, x: i32, y: i32| fill(color, [x as f64, y as f64, dot, dot], &map, g);
+        for &(x, y) in &self.game.level().walls {
+            mark(self.theme().border, x, y);
+        }
+        for food in self.game.foods() {
+            mark(self.theme().food(food.kind), food.x, food.y);
+        }
+        for player in 0..self.game.players() {
+            if self.game.config().arena && !self.game.is_alive(player) {
+                continue;
+            }
+            let color = self.theme().players[player % self.theme().players.len()];
+            for block in self.game.snake(player).blocks() {
+                mark(color, block.x, block.y);
+            }
+        }
+
+        let (x, y) = self.camera();
+        let (x, y, w, h) = (x as f64, y as f64, view_width as f64, view_height as f64);
+        let line = 1.0 / scale;
+        for &rect in &[
+            [x, y, w, line],
+            [x, y + h - line, w, line],
+            [x, y, line, h],
+            [x + w - line, y, line, h],
+        ] {
+            fill(self.theme().hud, rect, &map, g);
+        }
+    }
+
+    fn match_winner(&self) -> Option<usize> {
+        if !self.game.is_match_over() {
+            return None;
+        }
+        let rounds = self.game.config().rounds_to_win;
+        self.game.wins().iter().position(|&w| w >= rounds)
+    }
+
+    pub fn update(&mut self, delta_time: f64) {
+        if self.playback.is_some() {
+            self.update_playback(delta_time);
+            return;
+        }
+
+        match self.screen {
+            Screen::Playing => {}
+            Screen::GameOver => {
+                self.waiting_time += delta_time;
+                return;
+            }
+            Screen::Title | Screen::Paused => return,
+        }
+
+        self.waiting_time += delta_time;
+        if self.rewinding {
+            if self.waiting_time > REWIND_PERIOD {
+                if let Some(ref mut rewind) = self.rewind {
+                    rewind.step_back(&mut self.game);
+                }
+                self.waiting_time = 0.0;
+            }
+            return;
+        }
+
+        if self.waiting_time > self.moving_period() {
+            let mut dirs = Vec::with_capacity(self.game.players());
+            for player in 0..self.game.players() {
+                let dir = match self.controllers[player] {
+                    Some(ref mut controller) => controller.next_direction(&self.game, player),
+                    None => self.inputs[player].pop(),
+                };
+                dirs.push(dir);
+            }
+            match self.rewind {
+                Some(ref mut rewind) => rewind.record(&self.game),
+                None => self.recording.record_step(&dirs),
+            }
+            self.game.step_all(&dirs);
+            self.waiting_time = 0.0;
+
+            if self.game.is_game_over() {
+                // a practice game is neither replayable nor a fair score
+                if self.daily.is_some() {
+                    self.save_recording();
+                    self.save_daily_result();
+                } else if self.rewind.is_none() {
+                    self.save_best();
+                    self.save_recording();
+                }
+                self.screen = Screen::GameOver;
+            }
+        }
+    }
+
+    fn restart(&mut self) {
+        if self.daily.is_some() {
+            self.new_daily_run();
+        } else {
+            self.game.restart();
+            match self.rewind {
+                Some(ref mut rewind) => rewind.clear(),
+                None => self.recording.record_restart(),
+            }
+        }
+        for input in &mut self.inputs {
+            input.clear();
+        }
+        self.waiting_time = 0.0;
+        self.new_best = false;
+        self.screen = Screen::Playing;
+    }
+
+    /// Plays the replay at the chosen speed, waiting as long as the game
+    /// did whenever it restarts.
+    fn update_playback(&mut self, delta_time: f64) {
+        if self.screen == Screen::Paused {
+            return;
+        }
+        self.waiting_time += delta_time * REPLAY_SPEEDS[self.replay_speed];
+
+        let period = if self.game.is_game_over() {
+            RESTART_TIME
+        } else {
+            self.moving_period()
+        };
+        if self.waiting_time > period {
+            self.play_move();
+            self.waiting_time = 0.0;
+        }
+    }
+
+    fn play_move(&mut self) {
+        if let Some(ref mut playback) = self.playback {
+            playback.step(&mut self.game);
+        }
+    }
+
+    fn save_recording(&self) {
+        let saved = storage::replays_dir()
+            .and_then(|dir| self.recording.save(&dir.join(&self.recording_name)));
+        if let Err(e) = saved {
+            println!("Could not save the replay: {}", e);
+        }
+    }
+
+    fn save_daily_result(&mut self) {
+        let challenge = match self.daily {
+            Some(challenge) => challenge,
+            None => return,
+        };
+        let score = self.game.score(0);
+        if score > self.best {
+            self.best = score;
+            self.new_best = true;
+        }
+        let result = DailyResult {
+            date: challenge.date,
+            seed: challenge.seed,
+            score,
+            length: self.game.snake(0).len(),
+            replay: self.recording_name.clone(),
+        };
+        if let Err(e) = storage::save_daily_result(&result) {
+            println!("Could not save the daily result: {}", e);
+        }
+    }
+
+    fn save_best(&mut self) {
+        if self.game.config().is_versus() || self.game.score(0) <= self.best {
+            return;
+        }
+        self.best = self.game.score(0);
+        self.new_best = true;
+        if let Err(e) = storage::save_best_score(self.best) {
+            println!("Could not save the best score: {}", e);
+        }
+    }
+}
+
+/// A rounded cell filled out to the edges it shares with the blocks next
+/// to it, so only the outside of turns and the ends of a snake are round.
+fn draw_joint(
+    color: types::Color,
+    (x, y): (i32, i32),
+    neighbours: &[Option<Direction>],
+    con: &Context,
+    g: &mut G2d,
+) {
+    let (x, y) = (x as f64, y as f64);
+    fill_round(color, [x, y, 1.0, 1.0], 0.5, con, g);
+    for dir in neighbours.iter().filter_map(|&dir| dir) {
+        let half = match dir {
+            Direction::Up => [x, y, 1.0, 0.5],
+            Direction::Down => [x, y + 0.5, 1.0, 0.5],
+            Direction::Left => [x, y, 0.5, 1.0],
+            Direction::Right => [x + 0.5, y, 0.5, 1.0],
+        };
+        fill(color, half, con, g);
+    }
+}
+
+/// Fills `amount` of the cell at `(x, y)` from its `side` edge, with the
+/// other end rounded.
+fn draw_cell_part(
+    color: types::Color,
+    (x, y): (i32, i32),
+    side: Direction,
+    amount: f64,
+    con: &Context,
+    g: &mut G2d,
+) {
+    if amount <= 0.0 {
+        return;
+    }
+    let (x, y) = (x as f64, y as f64);
+    let part = |length: f64| match side {
+        Direction::Up => [x, y, 1.0, length],
+        Direction::Down => [x, y + 1.0 - length, 1.0, length],
+        Direction::Left => [x, y, length, 1.0],
+        Direction::Right => [x + 1.0 - length, y, length, 1.0],
+    };
+    fill_round(color, part(amount), amount.min(1.0) / 2.0, con, g);
+    fill(color, part(amount / 2.0), con, g);
+}
+
+/// Dashed border, telling the player the edges lead to the other side.
+fn draw_portal_border(theme: &Theme, width: i32, height: i32, con: &Context, g: &mut G2d) {
+    for x in 0..width {
+        let color = if x % 2 == 0 { theme.border } else { theme.portal };
+        draw_block(color, x, 0, con, g);
+        draw_block(color, x, height - 1, con, g);
+    }
+    for y in 0..height {
+        let color = if y % 2 == 0 { theme.border } else { theme.portal };
+        draw_block(color, 0, y, con, g);
+        draw_block(color, width - 1, y, con, g);
+    }
+}
@@ -0,0 +1,248 @@
+//! The daily challenge.
+//!
+//! Everyone playing on the same day (in UTC) gets the same seed: the same
+//! obstacles, the same rng behind the food and the same number of steps to
+//! score as much as they can. A run is kept with its seed and the replay of
+//! its input, which `verify` plays back to check the score.
+
+use std::collections::{HashSet, VecDeque};
+use std::fmt;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use rand::Rng;
+
+use game::{rng_from_seed, GameConfig};
+use level::Level;
+use replay::{Playback, Replay};
+use snake::Direction;
+
+/// Board of every challenge, border included.
+pub const BOARD: (i32, i32) = (40, 30);
+/// Steps a run lasts, two minutes at the starting speed.
+pub const STEPS: u32 = 600;
+/// Steps per second when a run starts, the same for everyone.
+pub const SPEED: f64 = 5.0;
+/// Walls are laid out as this many short straight pieces.
+const OBSTACLES: usize = 14;
+const MAX_OBSTACLE_LENGTH: i32 = 6;
+/// Level names start with this, followed by the date.
+const NAME_PREFIX: &str = "Daily ";
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Date {
+    pub year: i32,
+    pub month: u32,
+    pub day: u32,
+}
+
+impl Date {
+    /// Today in UTC.
+    pub fn today() -> Date {
+        let secs = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        Date::from_days((secs / 86_400) as i64)
+    }
+
+    /// The day `days` days after 1970-01-01.
+    pub fn from_days(days: i64) -> Date {
+        // civil calendar from a day count, in 400 year eras starting in March
+        let z = days + 719_468;
+        let era = if z >= 0 { z } else { z - 146_096 } / 146_097;
+        let day_of_era = z - era * 146_097;
+        let year_of_era =
+            (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let shifted_month = (5 * day_of_year + 2) / 153;
+        let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+        let month = if shifted_month < 10 { shifted_month + 3 } else { shifted_month - 9 };
+        let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+        Date {
+            year: year as i32,
+            month: month as u32,
+            day: day as u32,
+        }
+    }
+
+    /// Reads a `YYYY-MM-DD` date.
+    pub fn parse(text: &str) -> Option<Date> {
+        let mut parts = text.trim().splitn(3, '-');
+        let year = parts.next()?.parse().ok()?;
+        let month = parts.next()?.parse().ok()?;
+        let day = parts.next()?.parse().ok()?;
+        if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+            return None;
+        }
+        Some(Date { year, month, day })
+    }
+}
+
+impl fmt::Display for Date {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Challenge {
+    pub date: Date,
+    pub seed: u64,
+}
+
+impl Challenge {
+    pub fn new(date: Date) -> Challenge {
+        Challenge {
+            date,
+            seed: seed_for(date),
+        }
+    }
+
+    pub fn today() -> Challenge {
+        Challenge::new(Date::today())
+    }
+
+    /// One player on the challenge board, for `STEPS` steps.
+    pub fn config(&self) -> GameConfig {
+        GameConfig {
+            time_limit: Some(STEPS),
+            ..GameConfig::new(BOARD.0, BOARD.1)
+        }
+    }
+
+    /// The open board with the day's obstacles on it. They never cut the
+    /// board in two and keep clear of the snake's start.
+    pub fn level(&self) -> Level {
+        let mut level = Level::open(BOARD.0, BOARD.1);
+        level.name = format!("{}{}", NAME_PREFIX, self.date);
+
+        let mut keep_clear: Vec<(i32, i32)> = level.food.clone();
+        let (start_x, start_y) = level.start;
+        for y in start_y - 1..=start_y + 1 {
+            for x in 1..start_x + 8 {
+                keep_clear.push((x, y));
+            }
+        }
+
+        // a stream of its own, the game rng starts from the seed itself
+        let mut rng = rng_from_seed(!self.seed);
+        for _ in 0..OBSTACLES {
+            let (mut x, mut y) = (rng.gen_range(1, BOARD.0 - 1), rng.gen_range(1, BOARD.1 - 1));
+            let direction = *rng.choose(&[Direction::Right, Direction::Down]).unwrap();
+            for _ in 0..rng.gen_range(2, MAX_OBSTACLE_LENGTH + 1) {
+                if level.is_inside(x, y) && !keep_clear.contains(&(x, y)) && !level.is_wall(x, y) {
+                    level.walls.push((x, y));
+                    if !is_connected(&level) {
+                        level.walls.pop();
+                    }
+                }
+                match direction {
+                    Direction::Right => x += 1,
+                    _ => y += 1,
+                }
+            }
+        }
+        // in the order the map is read back, where the wall order changes
+        // which cells the food lands on
+        level.walls.sort_by_key(|&(x, y)| (y, x));
+        level
+    }
+
+    /// An empty recording of a run, to be played from `Replay::game`.
+    pub fn replay(&self) -> Replay {
+        Replay::new(self.config(), vec![self.level()], self.seed)
+    }
+
+    /// Plays `replay` back, returning the score of its run once it checked
+    /// the replay is a whole run of this challenge.
+    pub fn verify(&self, replay: &Replay) -> Result<u32, String> {
+        if replay.seed != self.seed || replay.config != self.config() || replay.levels != vec![self.level()] {
+            return Err(format!("the replay is not of the {} challenge", self.date));
+        }
+
+        let mut game = replay.game();
+        let mut playback = Playback::new(replay.clone());
+        while !game.is_game_over() {
+            if playback.restarts_next() {
+                return Err(String::from("the run was restarted before it was over"));
+            }
+            if playback.step(&mut game).is_none() {
+                return Err(String::from("the replay stops before the run is over"));
+            }
+        }
+        Ok(game.score(0))
+    }
+}
+
+/// Checks a recorded run, finding its challenge from the name of its level.
+pub fn verify(replay: &Replay) -> Result<(Date, u32), String> {
+    let date = replay.levels
+        .first()
+        .filter(|level| level.name.starts_with(NAME_PREFIX))
+        .and_then(|level| Date::parse(&level.name[NAME_PREFIX.len()..]))
+        .ok_or_else(|| String::from("the replay is not of a daily challenge"))?;
+    let score = Challenge::new(date).verify(replay)?;
+    Ok((date, score))
+}
+
+/// A finished run, as kept in the results file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DailyResult {
+    pub date: Date,
+    pub seed: u64,
+    pub score: u32,
+    pub length: usize,
+    /// File name of the run's replay in the replays directory.
+    pub replay: String,
+}
+
+impl DailyResult {
+    /// `date seed score length replay`, the seed in hex.
+    pub fn to_line(&self) -> String {
+        format!(
+            "{} {:016x} {} {} {}",
+            self.date, self.seed, self.score, self.length, self.replay
+        )
+    }
+
+    pub fn parse(line: &str) -> Option<DailyResult> {
+        let mut fields = line.split_whitespace();
+        let result = DailyResult {
+            date: Date::parse(fields.next()?)?,
+            seed: u64::from_str_radix(fields.next()?, 16).ok()?,
+            score: fields.next()?.parse().ok()?,
+            length: fields.next()?.parse().ok()?,
+            replay: fields.next()?.to_string(),
+        };
+        Some(result)
+    }
+}
+
+/// Mixes the date into a seed, splitmix64 style, so that days next to each
+/// other get seeds with nothing in common.
+fn seed_for(date: Date) -> u64 {
+    let mut z = ((date.year as u64) << 16 | (date.month as u64) << 8 | date.day as u64)
+        .wrapping_add(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+/// Whether every floor cell of `level` can be reached from its start.
+fn is_connected(level: &Level) -> bool {
+    let walls: HashSet<(i32, i32)> = level.walls.iter().cloned().collect();
+    let floor = ((level.width - 2) * (level.height - 2)) as usize - walls.len();
+
+    let mut seen = HashSet::new();
+    let mut queue = VecDeque::new();
+    seen.insert(level.start);
+    queue.push_back(level.start);
+    while let Some((x, y)) = queue.pop_front() {
+        for &next in &[(x + 1, y), (x - 1, y), (x, y + 1), (x, y - 1)] {
+            if level.is_inside(next.0, next.1) && !walls.contains(&next) && seen.insert(next) {
+                queue.push_back(next);
+            }
+        }
+    }
+    seen.len() == floor
+}
@@ -1,26 +1,42 @@
-use piston_window::{rectangle, Context, G2d};
+//! Drawing on the board.
+//!
+//! Blocks and rectangles are given in board cells, on a context scaled with
+//! `board_context` so that one unit is one cell. Text is placed in pixels
+//! on the window's own context. Shapes that end up outside the window are
+//! left out, so only the part of a big board in view costs anything.
+
+use std::f64::consts::PI;
+
+use piston_window::{rectangle, text, Context, G2d, G2dTexture, Glyphs, Image, Transformed};
 use piston_window::types::Color;
 
-const BLOCK_SIZE: f64 = 25.0;
+use rusty_snake::snake::Direction;
 
-pub fn to_coord(game_coord: i32) -> f64 {
-    (game_coord as f64) * BLOCK_SIZE
+/// `con` scaled so one unit is a cell of `cell_size` pixels.
+pub fn board_context(con: &Context, cell_size: f64) -> Context {
+    con.zoom(cell_size)
 }
 
-pub fn to_coord_u32(game_coord: i32) -> u32 {
-    to_coord(game_coord) as u32
+/// The cell under pixel `(x, y)` of a board drawn with `board_context`.
+pub fn to_cell(x: f64, y: f64, cell_size: f64) -> (i32, i32) {
+    ((x / cell_size).floor() as i32, (y / cell_size).floor() as i32)
 }
 
-pub fn draw_block(color: Color, x: i32, y: i32, con: &Context, g: &mut G2d) {
-    let gui_x = to_coord(x);
-    let gui_y = to_coord(y);
+/// Whether any of `rect` lands inside the window. The board is only ever
+/// scaled and moved, never turned, so two corners are enough to tell.
+fn is_visible(rect: [f64; 4], con: &Context) -> bool {
+    let t = con.transform;
+    let to_window = |x: f64, y: f64| {
+        (t[0][0] * x + t[0][1] * y + t[0][2], t[1][0] * x + t[1][1] * y + t[1][2])
+    };
+    let (x0, y0) = to_window(rect[0], rect[1]);
+    let (x1, y1) = to_window(rect[0] + rect[2], rect[1] + rect[3]);
+    // the window spans -1 to 1 both ways
+    x0.min(x1) <= 1.0 && x0.max(x1) >= -1.0 && y0.min(y1) <= 1.0 && y0.max(y1) >= -1.0
+}
 
-    rectangle(
-        color,
-        [gui_x, gui_y, BLOCK_SIZE, BLOCK_SIZE],
-        con.transform,
-        g,
-    );
+pub fn draw_block(color: Color, x: i32, y: i32, con: &Context, g: &mut G2d) {
+    fill(color, [x as f64, y as f64, 1.0, 1.0], con, g);
 }
 
 pub fn draw_rectangle(
@@ -32,18 +48,63 @@ pub fn draw_rectangle(
     con: &Context,
     g: &mut G2d,
 ) {
-    let x = to_coord(x);
-    let y = to_coord(y);
-
-    rectangle(
-        color,
-        [
-            x,
-            y,
-            BLOCK_SIZE * (width as f64),
-            BLOCK_SIZE * (height as f64),
-        ],
-        con.transform,
-        g,
-    );
-}
\ No newline at end of file
+    fill(color, [x as f64, y as f64, width as f64, height as f64], con, g);
+}
+
+/// Fills `rect`, `[x, y, width, height]` in cells.
+pub fn fill(color: Color, rect: [f64; 4], con: &Context, g: &mut G2d) {
+    if is_visible(rect, con) {
+        rectangle(color, rect, con.transform, g);
+    }
+}
+
+/// Fills `rect` with its corners rounded off by `radius` cells.
+pub fn fill_round(color: Color, rect: [f64; 4], radius: f64, con: &Context, g: &mut G2d) {
+    if is_visible(rect, con) {
+        rectangle::Rectangle::new_round(color, radius).draw(rect, &con.draw_state, con.transform, g);
+    }
+}
+
+/// Draws `texture` over cell `(x, y)`, tinted with `color` and turned from
+/// facing right to facing `dir`.
+pub fn draw_sprite(
+    texture: &G2dTexture,
+    color: Color,
+    (x, y): (i32, i32),
+    dir: Direction,
+    con: &Context,
+    g: &mut G2d,
+) {
+    let (x, y) = (x as f64, y as f64);
+    if !is_visible([x, y, 1.0, 1.0], con) {
+        return;
+    }
+    let angle = match dir {
+        Direction::Right => 0.0,
+        Direction::Down => PI / 2.0,
+        Direction::Left => PI,
+        Direction::Up => -PI / 2.0,
+    };
+    let transform = con.transform
+        .trans(x + 0.5, y + 0.5)
+        .rot_rad(angle)
+        .trans(-0.5, -0.5);
+    Image::new_color(color)
+        .rect([0.0, 0.0, 1.0, 1.0])
+        .draw(texture, &con.draw_state, transform, g);
+}
+
+/// Writes `line` in `style` with its baseline at pixel `(x, y)`.
+pub fn draw_text(
+    style: text::Text,
+    line: &str,
+    x: f64,
+    y: f64,
+    glyphs: &mut Glyphs,
+    con: &Context,
+    g: &mut G2d,
+) {
+    style
+        .draw(line, glyphs, &con.draw_state, con.transform.trans(x, y), g)
+        .ok();
+}
@@ -0,0 +1,348 @@
+//! Level editor.
+//!
+//! The left mouse button paints with the tool picked in the toolbar under
+//! the board, or with keys 1 to 5, and the right button erases. T tries the
+//! level out in practice mode, S saves it in the format the game loads and
+//! C changes the colors.
+
+use std::fs::File;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use piston_window::*;
+
+use rusty_snake::game::GameConfig;
+use rusty_snake::level::{Cell, Level};
+use rusty_snake::options::Options;
+use rusty_snake::snake::Direction;
+use rusty_snake::theme::Theme;
+
+use app::App;
+use draw::{board_context, draw_block, draw_rectangle, draw_text, fill, to_cell};
+use skin::Themes;
+
+const FONT_SIZE: u32 = 16;
+/// Pixels of each of the two toolbar rows.
+const ROW_HEIGHT: f64 = 24.0;
+/// Pixels across each tool button.
+const TOOL_WIDTH: f64 = 100.0;
+/// The window is at least this wide, for the toolbar to fit.
+const MIN_WIDTH: f64 = 5.0 * TOOL_WIDTH;
+
+const HELP: &str = "Arrows turn the start, T tries the level, S saves, C colors, Esc quits";
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum Tool {
+    Wall,
+    Food,
+    FoodZone,
+    Erase,
+    Start,
+}
+
+impl Tool {
+    fn all() -> [Tool; 5] {
+        [Tool::Wall, Tool::Food, Tool::FoodZone, Tool::Erase, Tool::Start]
+    }
+
+    fn name(&self) -> &'static str {
+        match *self {
+            Tool::Wall => "Wall",
+            Tool::Food => "Food",
+            Tool::FoodZone => "Food zone",
+            Tool::Erase => "Erase",
+            Tool::Start => "Start",
+        }
+    }
+
+    fn key(&self) -> Key {
+        match *self {
+            Tool::Wall => Key::D1,
+            Tool::Food => Key::D2,
+            Tool::FoodZone => Key::D3,
+            Tool::Erase => Key::D4,
+            Tool::Start => Key::D5,
+        }
+    }
+}
+
+pub struct Editor {
+    level: Level,
+    path: PathBuf,
+    tool: Tool,
+    /// Tool painting while a mouse button is held down.
+    painting: Option<Tool>,
+    /// Mouse position in pixels.
+    mouse: [f64; 2],
+    /// Help, or how the last save or try went.
+    status: String,
+    /// The level being tried out, `None` while editing.
+    test: Option<App>,
+    options: Options,
+    themes: Themes,
+}
+
+impl Editor {
+    /// Opens the level in `path`, or a new open board of the size asked for
+    /// in `options` when there is no such file yet.
+    pub fn open(path: &Path, options: &Options) -> Result<Editor, String> {
+        let level = if path.exists() {
+            Level::load(path)?
+        } else {
+            let (width, height) = options.board_size();
+            let mut level = Level::open_with_length(width, height, options.length.unwrap_or(3))?;
+            level.name = path.file_stem()
+                .and_then(|stem| stem.to_str())
+                .unwrap_or("Unnamed")
+                .to_string();
+            level
+        };
+
+        Ok(Editor {
+            level,
+            path: path.to_path_buf(),
+            tool: Tool::Wall,
+            painting: None,
+            mouse: [0.0, 0.0],
+            status: String::from(HELP),
+            test: None,
+            options: options.clone(),
+            themes: Themes::classic(),
+        })
+    }
+
+    /// The theme of the level being tried out, which C changes separately.
+    pub fn theme(&self) -> &Theme {
+        match self.test {
+            Some(ref app) => app.theme(),
+            None => self.themes.theme(),
+        }
+    }
+
+    pub fn set_themes(&mut self, themes: Themes) {
+        self.themes = themes;
+    }
+
+    /// The board with the toolbar under it, in pixels.
+    pub fn window_size(&self) -> [u32; 2] {
+        let width = self.pixels(self.level.width).max(MIN_WIDTH);
+        let height = self.pixels(self.level.height) + 2.0 * ROW_HEIGHT;
+        [width as u32, height as u32]
+    }
+
+    fn pixels(&self, cells: i32) -> f64 {
+        cells as f64 * self.options.cell_size
+    }
+
+    /// Returns false once the editor should close.
+    pub fn key_pressed(&mut self, key: Key) -> bool {
+        if key == Key::Escape && self.test.is_some() {
+            self.test = None;
+            self.status = String::from(HELP);
+            return true;
+        }
+        if let Some(ref mut app) = self.test {
+            app.key_pressed(key);
+            return true;
+        }
+
+        match key {
+            Key::Escape => return false,
+            Key::T => self.test_play(),
+            Key::S => self.save(),
+            Key::C => self.themes.next(),
+            Key::Up => self.turn_start(Direction::Up),
+            Key::Down => self.turn_start(Direction::Down),
+            Key::Left => self.turn_start(Direction::Left),
+            Key::Right => self.turn_start(Direction::Right),
+            _ => {
+                if let Some(&tool) = Tool::all().iter().find(|tool| tool.key() == key) {
+                    self.tool = tool;
+                }
+            }
+        }
+        true
+    }
+
+    pub fn key_released(&mut self, key: Key) {
+        if let Some(ref mut app) = self.test {
+            app.key_released(key);
+        }
+    }
+
+    pub fn mouse_moved(&mut self, position: [f64; 2]) {
+        self.mouse = position;
+        if let Some(tool) = self.painting {
+            self.apply(tool);
+        }
+    }
+
+    pub fn mouse_pressed(&mut self, button: MouseButton) {
+        if self.test.is_some() {
+            return;
+        }
+        if let Some(tool) = self.toolbar_tool() {
+            self.tool = tool;
+            return;
+        }
+
+        let tool = match button {
+            MouseButton::Left => self.tool,
+            MouseButton::Right => Tool::Erase,
+            _ => return,
+        };
+        self.apply(tool);
+        // the start goes where it was clicked, it doesn't follow a drag
+        if tool != Tool::Start {
+            self.painting = Some(tool);
+        }
+    }
+
+    pub fn mouse_released(&mut self) {
+        self.painting = None;
+    }
+
+    /// Tool button under the mouse.
+    fn toolbar_tool(&self) -> Option<Tool> {
+        let top = self.pixels(self.level.height);
+        if self.mouse[1] < top || self.mouse[1] >= top + ROW_HEIGHT {
+            return None;
+        }
+        Tool::all().get((self.mouse[0] / TOOL_WIDTH) as usize).cloned()
+    }
+
+    fn apply(&mut self, tool: Tool) {
+        let (x, y) = to_cell(self.mouse[0], self.mouse[1], self.options.cell_size);
+        if !self.level.is_inside(x, y) {
+            return;
+        }
+        match tool {
+            Tool::Wall => self.level.set_cell(x, y, Cell::Wall),
+            Tool::Food => self.level.set_cell(x, y, Cell::Food),
+            Tool::FoodZone => self.level.set_cell(x, y, Cell::FoodZone),
+            Tool::Erase => self.level.set_cell(x, y, Cell::Floor),
+            Tool::Start => {
+                let direction = self.level.direction;
+                if let Err(e) = self.level.set_start((x, y), direction) {
+                    self.status = e;
+                }
+            }
+        }
+    }
+
+    fn turn_start(&mut self, direction: Direction) {
+        let start = self.level.start;
+        if let Err(e) = self.level.set_start(start, direction) {
+            self.status = e;
+        }
+    }
+
+    /// Plays the level as it is, in practice mode so nothing gets saved.
+    fn test_play(&mut self) {
+        if let Err(e) = self.level.check() {
+            self.status = e;
+            return;
+        }
+        let mut options = self.options.clone();
+        options.practice = true;
+        let config = GameConfig::new(self.level.width, self.level.height);
+        let mut app = App::new(config, vec![self.level.clone()], &options);
+        app.set_themes(self.themes.clone());
+        self.test = Some(app);
+        self.painting = None;
+    }
+
+    fn save(&mut self) {
+        let saved = self.level.check().and_then(|_| {
+            File::create(&self.path)
+                .and_then(|mut file| file.write_all(self.level.to_text().as_bytes()))
+                .map_err(|e| format!("{}: {}", self.path.display(), e))
+        });
+        self.status = match saved {
+            Ok(()) => format!("Saved {}", self.path.display()),
+            Err(e) => e,
+        };
+    }
+
+    pub fn update(&mut self, delta_time: f64) {
+        if let Some(ref mut app) = self.test {
+            app.update(delta_time);
+        }
+    }
+
+    pub fn draw(&self, con: &Context, g: &mut G2d, glyphs: &mut Glyphs) {
+        match self.test {
+            Some(ref app) => app.draw(con, g, glyphs),
+            None => self.draw_level(con, g),
+        }
+        self.draw_toolbar(con, g, glyphs);
+    }
+
+    fn draw_level(&self, con: &Context, g: &mut G2d) {
+        let theme = self.themes.theme();
+        let level = &self.level;
+        let board = board_context(con, self.options.cell_size);
+
+        let (width, height) = (level.width, level.height);
+        draw_rectangle(theme.border, 0, 0, width, 1, &board, g);
+        draw_rectangle(theme.border, 0, height - 1, width, 1, &board, g);
+        draw_rectangle(theme.border, 0, 0, 1, height, &board, g);
+        draw_rectangle(theme.border, width - 1, 0, 1, height, &board, g);
+
+        for &(x, y) in &level.walls {
+            draw_block(theme.border, x, y, &board, g);
+        }
+        let zone = [theme.apple[0], theme.apple[1], theme.apple[2], 0.3];
+        for &(x, y) in &level.food_zones {
+            draw_block(zone, x, y, &board, g);
+        }
+        for &(x, y) in &level.food {
+            draw_block(theme.apple, x, y, &board, g);
+        }
+
+        for (x, y) in level.start_body() {
+            draw_block(theme.players[0], x, y, &board, g);
+        }
+        // a bar on the front of the head shows which way the snake starts
+        let (x, y) = (level.start.0 as f64, level.start.1 as f64);
+        let front = match level.direction {
+            Direction::Up => [x, y, 1.0, 0.3],
+            Direction::Down => [x, y + 0.7, 1.0, 0.3],
+            Direction::Left => [x, y, 0.3, 1.0],
+            Direction::Right => [x + 0.7, y, 0.3, 1.0],
+        };
+        fill(theme.hud, front, &board, g);
+
+        let (x, y) = to_cell(self.mouse[0], self.mouse[1], self.options.cell_size);
+        if level.is_inside(x, y) {
+            let hover = [theme.hud[0], theme.hud[1], theme.hud[2], 0.3];
+            draw_block(hover, x, y, &board, g);
+        }
+    }
+
+    /// Tool buttons on the first row, help or the last message on the second.
+    fn draw_toolbar(&self, con: &Context, g: &mut G2d, glyphs: &mut Glyphs) {
+        let top = self.pixels(self.level.height);
+        let width = self.window_size()[0] as f64;
+        fill(self.theme().screen, [0.0, top, width, 2.0 * ROW_HEIGHT], con, g);
+
+        let style = text::Text::new_color(self.theme().hud, FONT_SIZE);
+        let baseline = ROW_HEIGHT - 7.0;
+        if self.test.is_none() {
+            for (i, &tool) in Tool::all().iter().enumerate() {
+                let x = i as f64 * TOOL_WIDTH;
+                if tool == self.tool {
+                    fill(self.theme().portal, [x, top, TOOL_WIDTH, ROW_HEIGHT], con, g);
+                }
+                let label = format!("{} {}", i + 1, tool.name());
+                draw_text(style, &label, x + 6.0, top + baseline, glyphs, con, g);
+            }
+        }
+
+        let status = match self.test {
+            Some(_) => "Trying the level out, Esc goes back to editing",
+            None => &self.status,
+        };
+        draw_text(style, status, 6.0, top + ROW_HEIGHT + baseline, glyphs, con, g);
+    }
+}
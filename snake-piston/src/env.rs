@@ -0,0 +1,399 @@
+//! Reinforcement learning environment in the style of OpenAI Gym.
+//!
+//! An `Env` wraps a single player `Game`: `reset` starts an episode from a
+//! seed and `step` plays one action, returning the new observation, the
+//! reward and whether the episode is over. `VecEnv` steps a batch of them
+//! on several threads. Nothing here needs a window.
+//!
+//! ```
+//! use rusty_snake::env::{Action, Env, EnvConfig};
+//!
+//! let mut env = Env::new(EnvConfig::new(12, 12));
+//! let mut observation = env.reset(7);
+//! loop {
+//!     let (next, _reward, done) = env.step(Action::Straight);
+//!     observation = next;
+//!     if done {
+//!         break;
+//!     }
+//! }
+//! assert_eq!(observation.len(), env.observation_size());
+//! ```
+
+use std::cmp;
+use std::thread;
+
+use game::{Event, Game, GameConfig};
+use options::MIN_SIZE;
+use snake::Direction;
+
+/// Channels of a `Observation::Grid` observation.
+pub const GRID_CHANNELS: usize = 4;
+/// Length of a `Observation::Features` observation.
+pub const FEATURES: usize = 12;
+
+/// A move relative to where the snake is heading, so it can never reverse.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Action {
+    Straight,
+    Left,
+    Right,
+}
+
+impl Action {
+    pub fn all() -> [Action; 3] {
+        [Action::Straight, Action::Left, Action::Right]
+    }
+
+    /// The action numbered `index` in `all`, for agents that pick numbers.
+    pub fn from_index(index: usize) -> Option<Action> {
+        Action::all().get(index).cloned()
+    }
+
+    fn direction(&self, heading: Direction) -> Direction {
+        match *self {
+            Action::Straight => heading,
+            Action::Left => heading.turn_left(),
+            Action::Right => heading.turn_right(),
+        }
+    }
+}
+
+/// What the agent gets to see.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Observation {
+    /// `GRID_CHANNELS` planes of the whole board, row by row, each cell 0
+    /// or 1: walls and border, snake body, snake head, food.
+    Grid,
+    /// `FEATURES` numbers around the head: danger straight, left and right,
+    /// heading up, down, left and right, food up, down, left and right,
+    /// and the snake length over the size of the board.
+    Features,
+}
+
+/// Rewards for what happens on a step, added up.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Rewards {
+    /// Eating food that makes the snake grow.
+    pub food: f32,
+    pub death: f32,
+    /// Filling the board.
+    pub win: f32,
+    /// Every step, negative to hurry the snake up.
+    pub step: f32,
+    /// Moving closer to the apple, and minus this for moving away.
+    pub closer: f32,
+}
+
+impl Default for Rewards {
+    fn default() -> Rewards {
+        Rewards {
+            food: 1.0,
+            death: -1.0,
+            win: 10.0,
+            step: 0.0,
+            closer: 0.0,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct EnvConfig {
+    /// Board to play on, always with a single player.
+    pub game: GameConfig,
+    pub observation: Observation,
+    pub rewards: Rewards,
+    /// Ends the episode after this many steps without eating, so a snake
+    /// going round in circles doesn't play forever.
+    pub hunger: Option<usize>,
+}
+
+impl EnvConfig {
+    pub fn new(width: i32, height: i32) -> EnvConfig {
+        EnvConfig {
+            game: GameConfig::new(width, height),
+            observation: Observation::Features,
+            rewards: Rewards::default(),
+            hunger: Some((width * height) as usize),
+        }
+    }
+}
+
+pub struct Env {
+    config: EnvConfig,
+    game: Game,
+    hungry_steps: usize,
+    done: bool,
+}
+
+impl Env {
+    /// An environment ready to play, as if `reset(0)` was called.
+    ///
+    /// Panics on a board smaller than `MIN_SIZE` either way, where the
+    /// snake or the first apple would be off the board.
+    pub fn new(mut config: EnvConfig) -> Env {
+        let (width, height) = (config.game.width, config.game.height);
+        assert!(
+            width >= MIN_SIZE && height >= MIN_SIZE,
+            "the board must be at least {}x{}, not {}x{}",
+            MIN_SIZE,
+            MIN_SIZE,
+            width,
+            height
+        );
+        config.game.players = 1;
+        Env {
+            game: Game::with_config(config.game, 0),
+            config,
+            hungry_steps: 0,
+            done: false,
+        }
+    }
+
+    pub fn config(&self) -> &EnvConfig {
+        &self.config
+    }
+
+    pub fn game(&self) -> &Game {
+        &self.game
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.done
+    }
+
+    /// Starts a new episode, the same one for the same seed.
+    pub fn reset(&mut self, seed: u64) -> Vec<f32> {
+        self.game = Game::with_config(self.config.game, seed);
+        self.hungry_steps = 0;
+        self.done = false;
+        self.observe()
+    }
+
+    /// Plays `action`. Once the episode is done it stays done, with no
+    /// more reward, until `reset`.
+    pub fn step(&mut self, action: Action) -> (Vec<f32>, f32, bool) {
+        if self.done {
+            return (self.observe(), 0.0, true);
+        }
+
+        let rewards = self.config.rewards;
+        let before = self.apple_distance();
+        let heading = self.game.snake(0).head_direction();
+        let events = self.game.step(Some(action.direction(heading)));
+
+        let mut reward = rewards.step;
+        self.hungry_steps += 1;
+        for event in &events {
+            match *event {
+                Event::Ate { kind, .. } if kind.grows() => {
+                    reward += rewards.food;
+                    self.hungry_steps = 0;
+                }
+                Event::Died { .. } => reward += rewards.death,
+                Event::BoardFilled => reward += rewards.win,
+                _ => {}
+            }
+        }
+        // after eating the apple is somewhere else, distances don't compare
+        let ate = self.hungry_steps == 0;
+        if !ate && !self.game.is_game_over() {
+            if let (Some(before), Some(after)) = (before, self.apple_distance()) {
+                if after < before {
+                    reward += rewards.closer;
+                } else if after > before {
+                    reward -= rewards.closer;
+                }
+            }
+        }
+
+        let starved = matches!(self.config.hunger, Some(limit) if self.hungry_steps >= limit);
+        self.done = self.game.is_game_over() || starved;
+        (self.observe(), reward, self.done)
+    }
+
+    /// Size of each dimension of an observation, outermost first.
+    pub fn observation_shape(&self) -> Vec<usize> {
+        match self.config.observation {
+            Observation::Grid => vec![
+                GRID_CHANNELS,
+                self.game.height() as usize,
+                self.game.width() as usize,
+            ],
+            Observation::Features => vec![FEATURES],
+        }
+    }
+
+    pub fn observation_size(&self) -> usize {
+        self.observation_shape().iter().product()
+    }
+
+    pub fn observe(&self) -> Vec<f32> {
+        match self.config.observation {
+            Observation::Grid => self.grid(),
+            Observation::Features => self.features(),
+        }
+    }
+
+    fn grid(&self) -> Vec<f32> {
+        let (width, height) = (self.game.width(), self.game.height());
+        let plane = (width * height) as usize;
+        let mut grid = vec![0.0; GRID_CHANNELS * plane];
+        let mut set = |channel: usize, x: i32, y: i32| {
+            grid[channel * plane + (y * width + x) as usize] = 1.0;
+        };
+
+        let bounds = self.game.bounds();
+        for y in 0..height {
+            for x in 0..width {
+                if !bounds.contains(x, y) || self.game.is_wall(x, y) {
+                    set(0, x, y);
+                }
+            }
+        }
+        for block in self.game.snake(0).blocks() {
+            set(1, block.x, block.y);
+        }
+        let (head_x, head_y) = self.game.snake(0).head_position();
+        set(2, head_x, head_y);
+        for food in self.game.foods() {
+            set(3, food.x, food.y);
+        }
+        grid
+    }
+
+    fn features(&self) -> Vec<f32> {
+        let snake = self.game.snake(0);
+        let heading = snake.head_direction();
+        let (head_x, head_y) = snake.head_position();
+        let flag = |b: bool| if b { 1.0 } else { 0.0 };
+
+        let mut features = Vec::with_capacity(FEATURES);
+        for &action in &Action::all() {
+            let (x, y) = self.game.next_cell(head_x, head_y, action.direction(heading));
+            let tail = snake.len() > 2 && snake.tail_position() == (x, y);
+            features.push(flag(!self.game.is_free(x, y) && !tail));
+        }
+        for &dir in &Direction::all() {
+            features.push(flag(heading == dir));
+        }
+        let (food_x, food_y) = self.game.food().unwrap_or((head_x, head_y));
+        features.push(flag(food_y < head_y));
+        features.push(flag(food_y > head_y));
+        features.push(flag(food_x < head_x));
+        features.push(flag(food_x > head_x));
+
+        let bounds = self.game.bounds();
+        let cells = (bounds.right - bounds.left + 1) * (bounds.bottom - bounds.top + 1);
+        features.push(snake.len() as f32 / cells as f32);
+        features
+    }
+
+    /// Steps from the head to the apple, ignoring anything in the way.
+    fn apple_distance(&self) -> Option<i32> {
+        let (head_x, head_y) = self.game.snake(0).head_position();
+        let (food_x, food_y) = self.game.food()?;
+        let (mut dx, mut dy) = ((food_x - head_x).abs(), (food_y - head_y).abs());
+        if self.game.is_wrapping() {
+            let bounds = self.game.bounds();
+            dx = cmp::min(dx, bounds.right - bounds.left + 1 - dx);
+            dy = cmp::min(dy, bounds.bottom - bounds.top + 1 - dy);
+        }
+        Some(dx + dy)
+    }
+}
+
+/// Environments a thread steps at least, fewer aren't worth a thread.
+const MIN_ENVS_PER_THREAD: usize = 16;
+
+/// A batch of environments stepped together, spread over the cores.
+///
+/// Episodes that end are started again right away with the next seed of
+/// that environment, so every `step` returns a usable observation: when
+/// `done` is set it is the first one of the new episode.
+pub struct VecEnv {
+    envs: Vec<Env>,
+    /// Seed of the next episode of each environment.
+    seeds: Vec<u64>,
+    threads: usize,
+}
+
+impl VecEnv {
+    pub fn new(config: EnvConfig, count: usize) -> VecEnv {
+        let threads = thread::available_parallelism().map_or(1, |n| n.get());
+        VecEnv {
+            envs: (0..count).map(|_| Env::new(config)).collect(),
+            seeds: vec![0; count],
+            threads: cmp::max(1, cmp::min(threads, count / MIN_ENVS_PER_THREAD)),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.envs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.envs.is_empty()
+    }
+
+    pub fn envs(&self) -> &[Env] {
+        &self.envs
+    }
+
+    /// Resets every environment, the `i`th one from `seed + i`. Later
+    /// episodes of an environment take seeds `len()` apart.
+    pub fn reset(&mut self, seed: u64) -> Vec<Vec<f32>> {
+        let count = self.envs.len() as u64;
+        let mut observations = Vec::with_capacity(self.envs.len());
+        for (i, env) in self.envs.iter_mut().enumerate() {
+            let seed = seed.wrapping_add(i as u64);
+            self.seeds[i] = seed.wrapping_add(count);
+            observations.push(env.reset(seed));
+        }
+        observations
+    }
+
+    /// Plays `actions[i]` in the `i`th environment.
+    pub fn step(&mut self, actions: &[Action]) -> Vec<(Vec<f32>, f32, bool)> {
+        assert_eq!(actions.len(), self.envs.len(), "one action per environment");
+        let count = self.envs.len() as u64;
+        let mut results = vec![(Vec::new(), 0.0, false); self.envs.len()];
+        // a small batch steps faster than threads start
+        if self.threads == 1 {
+            step_envs(&mut self.envs, &mut self.seeds, actions, &mut results, count);
+            return results;
+        }
+
+        let chunk = self.envs.len().div_ceil(self.threads);
+        thread::scope(|scope| {
+            let parts = self.envs
+                .chunks_mut(chunk)
+                .zip(self.seeds.chunks_mut(chunk))
+                .zip(actions.chunks(chunk))
+                .zip(results.chunks_mut(chunk));
+            for (((envs, seeds), actions), results) in parts {
+                scope.spawn(move || step_envs(envs, seeds, actions, results, count));
+            }
+        });
+        results
+    }
+}
+
+/// Steps each of `envs`, starting the ones that are done again from their
+/// seed in `seeds`, which then moves on by `count`.
+fn step_envs(
+    envs: &mut [Env],
+    seeds: &mut [u64],
+    actions: &[Action],
+    results: &mut [(Vec<f32>, f32, bool)],
+    count: u64,
+) {
+    for (i, env) in envs.iter_mut().enumerate() {
+        let (mut observation, reward, done) = env.step(actions[i]);
+        if done {
+            observation = env.reset(seeds[i]);
+            seeds[i] = seeds[i].wrapping_add(count);
+        }
+        results[i] = (observation, reward, done);
+    }
+}
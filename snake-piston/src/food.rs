@@ -0,0 +1,111 @@
+//! Things to eat and what they do to the snake.
+
+/// Blocks a shrink item takes off the tail.
+pub const SHRINK_BLOCKS: usize = 3;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum FoodKind {
+    /// The plain food, there is always one on the board.
+    Apple,
+    /// Worth more than an apple, but only there for a little while.
+    Bonus,
+    Shrink,
+    Slow,
+    Fast,
+    Ghost,
+    /// Left behind by a snake that died in the arena.
+    Remains,
+}
+
+impl FoodKind {
+    /// Every kind besides the apple, which show up now and then.
+    pub fn specials() -> [FoodKind; 5] {
+        [
+            FoodKind::Bonus,
+            FoodKind::Shrink,
+            FoodKind::Slow,
+            FoodKind::Fast,
+            FoodKind::Ghost,
+        ]
+    }
+
+    /// Whether eating it makes the snake one block longer.
+    pub fn grows(&self) -> bool {
+        matches!(*self, FoodKind::Apple | FoodKind::Bonus | FoodKind::Remains)
+    }
+
+    /// Points on the first level, later levels multiply them.
+    pub fn points(&self) -> u32 {
+        match *self {
+            FoodKind::Apple => 10,
+            FoodKind::Bonus => 50,
+            FoodKind::Remains => 5,
+            _ => 0,
+        }
+    }
+
+    pub fn effect(&self) -> Option<Effect> {
+        match *self {
+            FoodKind::Slow => Some(Effect::Slow),
+            FoodKind::Fast => Some(Effect::Fast),
+            FoodKind::Ghost => Some(Effect::Ghost),
+            _ => None,
+        }
+    }
+
+    /// Steps it stays on the board before going away, `None` for good.
+    pub fn lifetime(&self) -> Option<u32> {
+        match *self {
+            FoodKind::Apple | FoodKind::Remains => None,
+            FoodKind::Bonus => Some(40),
+            _ => Some(60),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Food {
+    pub kind: FoodKind,
+    pub x: i32,
+    pub y: i32,
+    /// Steps until it goes away, `None` when it stays.
+    pub steps_left: Option<u32>,
+}
+
+impl Food {
+    pub fn new(kind: FoodKind, x: i32, y: i32) -> Food {
+        Food {
+            kind,
+            x,
+            y,
+            steps_left: kind.lifetime(),
+        }
+    }
+}
+
+/// Timed change to how a snake plays, started by eating an item.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Effect {
+    /// The game runs slower.
+    Slow,
+    /// The game runs faster.
+    Fast,
+    /// The snake can cross its own body.
+    Ghost,
+}
+
+impl Effect {
+    /// Steps the effect lasts.
+    pub fn duration(&self) -> u32 {
+        match *self {
+            Effect::Slow | Effect::Fast => 40,
+            Effect::Ghost => 25,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ActiveEffect {
+    pub effect: Effect,
+    pub steps_left: u32,
+}
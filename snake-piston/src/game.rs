@@ -1,155 +1,830 @@
-use piston_window::*;
-use piston_window::types::Color;
+use std::sync::Arc;
 
-use rand::{thread_rng, Rng};
+use rand::{Rng, SeedableRng, XorShiftRng};
 
-use snake::{Direction, Snake};
-use draw::{draw_block, draw_rectangle};
+use food::{ActiveEffect, Effect, Food, FoodKind, SHRINK_BLOCKS};
+use grid::Grid;
+use level::Level;
+use snake::{Bounds, Direction, Snake};
 
-const FOOD_COLOR: Color = [0.80, 0.00, 0.00, 1.0];
-const BORDER_COLOR: Color = [0.00, 0.00, 0.00, 1.0];
-const GAMEOVER_COLOR: Color = [0.90, 0.00, 0.00, 0.4];
+/// Something that happened during a single `Game::step`.
+///
+/// `player` is the index of the snake, 0 for the first player.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Event {
+    Moved { player: usize, x: i32, y: i32 },
+    Ate { player: usize, kind: FoodKind, x: i32, y: i32 },
+    Grew { player: usize, length: usize },
+    Shrank { player: usize, length: usize },
+    EffectStarted { player: usize, effect: Effect },
+    EffectEnded { player: usize, effect: Effect },
+    FoodSpawned { kind: FoodKind, x: i32, y: i32 },
+    FoodExpired { kind: FoodKind, x: i32, y: i32 },
+    LevelUp { level: usize },
+    Died { player: usize },
+    /// Versus only: the round is over, `winner` is `None` on a draw.
+    RoundOver { winner: Option<usize> },
+    /// Versus only: `winner` got to `GameConfig::rounds_to_win`.
+    MatchOver { winner: usize },
+    /// The snakes fill the whole board, the game is won. In versus the
+    /// round goes to the longest snake.
+    BoardFilled,
+    /// `GameConfig::time_limit` steps were played. In versus the round
+    /// goes to the highest score.
+    TimeUp,
+}
 
-const MOVING_PERIOD: f64 = 0.2;
-const RESTART_TIME: f64 = 3.0;
+/// How a game is set up, fixed for its whole length.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct GameConfig {
+    pub width: i32,
+    pub height: i32,
+    /// Leaving the board on one edge comes back in on the opposite one
+    /// instead of killing the snake.
+    pub wrap: bool,
+    /// Number of snakes on the board, up to `MAX_PLAYERS`. With more than
+    /// one the game is played in rounds, the last snake alive winning the
+    /// round.
+    pub players: usize,
+    pub rounds_to_win: u32,
+    /// Dead snakes are cleared off the board, leaving food where they were.
+    pub arena: bool,
+    /// Steps the game lasts at most, `None` to play until the snakes die.
+    pub time_limit: Option<u32>,
+}
 
-pub struct Game {
+impl GameConfig {
+    pub fn new(width: i32, height: i32) -> GameConfig {
+        GameConfig {
+            width,
+            height,
+            wrap: false,
+            players: 1,
+            rounds_to_win: 3,
+            arena: false,
+            time_limit: None,
+        }
+    }
+
+    pub fn versus(width: i32, height: i32) -> GameConfig {
+        GameConfig {
+            players: 2,
+            ..GameConfig::new(width, height)
+        }
+    }
+
+    /// `players` snakes for a single round, the last one alive wins.
+    pub fn arena(width: i32, height: i32, players: usize) -> GameConfig {
+        GameConfig {
+            players,
+            rounds_to_win: 1,
+            arena: true,
+            ..GameConfig::new(width, height)
+        }
+    }
+
+    pub fn is_versus(&self) -> bool {
+        self.players > 1
+    }
+}
+
+/// Most snakes a game can have.
+pub const MAX_PLAYERS: usize = 8;
+
+/// Most special items on the board at once.
+const MAX_SPECIALS: usize = 2;
+/// One step in this many a special item shows up, if there is room.
+const SPECIAL_CHANCE: u32 = 40;
+
+#[derive(Clone)]
+struct Player {
     snake: Snake,
+    alive: bool,
+    score: u32,
+    effects: Vec<ActiveEffect>,
+}
+
+/// Snake rules without any rendering or timing.
+///
+/// Every call to `step` advances the snakes one cell, so the caller decides
+/// how often that happens. All randomness comes from the rng handed in at
+/// construction, which makes a game fully reproducible from its seed.
+#[derive(Clone)]
+pub struct Game {
+    players: Vec<Player>,
+    wins: Vec<u32>,
+
+    foods: Vec<Food>,
 
-    food_exists: bool,
-    food_x: i32,
-    food_y: i32,
+    config: GameConfig,
 
-    width: i32,
-    height: i32,
+    levels: Arc<Vec<Level>>,
+    level: usize,
+    /// Walls, snakes and food of the current level by cell.
+    grid: Grid,
+    apples: u32,
 
     game_over: bool,
-    waiting_time: f64,
+    match_over: bool,
+    /// The game ended with the board full.
+    won: bool,
+    /// Steps played since the game or round started.
+    steps: u32,
+    rng: XorShiftRng,
+}
+
+/// A game as it was at one point, to go back to with `Game::restore`.
+///
+/// It leaves out the grid, which is built again from the rest, so it costs
+/// about as much memory as the snakes and food on the board.
+#[derive(Clone)]
+pub struct Snapshot {
+    players: Vec<Player>,
+    wins: Vec<u32>,
+    foods: Vec<Food>,
+    level: usize,
+    apples: u32,
+    game_over: bool,
+    match_over: bool,
+    won: bool,
+    steps: u32,
+    rng: XorShiftRng,
+}
+
+/// Builds the game rng from a single number.
+pub fn rng_from_seed(seed: u64) -> XorShiftRng {
+    let lo = seed as u32;
+    let hi = (seed >> 32) as u32;
+    // xorshift must not start from an all zero state
+    XorShiftRng::from_seed([lo, hi, lo ^ 0x9E37_79B9, hi ^ 0x7F4A_7C15])
 }
 
 impl Game {
-    pub fn new(width: i32, height: i32) -> Game {
-        Game {
-            snake: Snake::new(2, 2),
-            waiting_time: 0.0,
-            food_exists: true,
-            food_x: 6,
-            food_y: 4,
-            width,
-            height,
-            game_over: false
-        }
+    pub fn new(width: i32, height: i32, seed: u64) -> Game {
+        Game::with_config(GameConfig::new(width, height), seed)
     }
 
-    pub fn key_pressed(&mut self, key: Key) {
-        if self.game_over {
-            return;
-        }
+    pub fn with_config(config: GameConfig, seed: u64) -> Game {
+        Game::with_rng(config, rng_from_seed(seed))
+    }
 
-        let dir = match key {
-            Key::Up => Some(Direction::Up),
-            Key::Down => Some(Direction::Down),
-            Key::Left => Some(Direction::Left),
-            Key::Right => Some(Direction::Right),
-            _ => None
+    pub fn with_rng(config: GameConfig, rng: XorShiftRng) -> Game {
+        let levels = vec![Level::open(config.width, config.height)];
+        Game::with_levels(config, Arc::new(levels), rng)
+    }
+
+    /// Plays through `levels` in order. The board takes the size of the
+    /// first level, so `config.width` and `config.height` are ignored.
+    pub fn with_levels(mut config: GameConfig, levels: Arc<Vec<Level>>, rng: XorShiftRng) -> Game {
+        assert!(!levels.is_empty(), "a game needs at least one level");
+        config.width = levels[0].width;
+        config.height = levels[0].height;
+
+        assert!(config.players > 0, "a game needs at least one player");
+        assert!(config.players <= MAX_PLAYERS, "too many players");
+
+        let mut game = Game {
+            players: Vec::new(),
+            wins: vec![0; config.players],
+            foods: Vec::new(),
+            config,
+            levels,
+            level: 0,
+            grid: Grid::new(config.width, config.height, Bounds {
+                left: 1,
+                top: 1,
+                right: config.width - 2,
+                bottom: config.height - 2,
+            }),
+            apples: 0,
+            game_over: false,
+            match_over: false,
+            won: false,
+            steps: 0,
+            rng,
         };
+        game.load_level(0);
+        game
+    }
+
+    pub fn config(&self) -> &GameConfig {
+        &self.config
+    }
+
+    pub fn players(&self) -> usize {
+        self.players.len()
+    }
+
+    pub fn snake(&self, player: usize) -> &Snake {
+        &self.players[player].snake
+    }
+
+    pub fn is_alive(&self, player: usize) -> bool {
+        self.players[player].alive
+    }
+
+    /// Points earned by `player` since the game or round started.
+    pub fn score(&self, player: usize) -> u32 {
+        self.players[player].score
+    }
+
+    /// Rounds won by each player in the current match.
+    pub fn wins(&self) -> &[u32] {
+        &self.wins
+    }
+
+    /// Effects running on `player`, with the steps they have left.
+    pub fn effects(&self, player: usize) -> &[ActiveEffect] {
+        &self.players[player].effects
+    }
+
+    pub fn has_effect(&self, player: usize, effect: Effect) -> bool {
+        self.players[player].effects.iter().any(|e| e.effect == effect)
+    }
+
+    /// Where the apple is.
+    pub fn food(&self) -> Option<(i32, i32)> {
+        self.foods
+            .iter()
+            .find(|f| f.kind == FoodKind::Apple)
+            .map(|f| (f.x, f.y))
+    }
+
+    /// Every item on the board, apples and specials.
+    pub fn foods(&self) -> &[Food] {
+        &self.foods
+    }
+
+    fn food_at(&self, x: i32, y: i32) -> Option<usize> {
+        self.foods.iter().position(|f| f.x == x && f.y == y)
+    }
+
+    pub fn width(&self) -> i32 {
+        self.config.width
+    }
+
+    pub fn height(&self) -> i32 {
+        self.config.height
+    }
+
+    pub fn is_wrapping(&self) -> bool {
+        self.config.wrap
+    }
 
-        if dir == None {
-            return
+    /// Cells inside the border, where the snake and food can be.
+    pub fn bounds(&self) -> Bounds {
+        Bounds {
+            left: 1,
+            top: 1,
+            right: self.config.width - 2,
+            bottom: self.config.height - 2,
         }
+    }
 
-        if dir.unwrap() == self.snake.head_direction().opposite() {
-            return;
+    pub fn level(&self) -> &Level {
+        &self.levels[self.level]
+    }
+
+    pub fn level_number(&self) -> usize {
+        self.level + 1
+    }
+
+    pub fn is_wall(&self, x: i32, y: i32) -> bool {
+        self.grid.is_wall(x, y)
+    }
+
+    /// Whether `(x, y)` is inside the board and neither wall nor snake.
+    pub fn is_free(&self, x: i32, y: i32) -> bool {
+        self.bounds().contains(x, y) && !self.grid.is_wall(x, y) && !self.grid.has_snake(x, y)
+    }
+
+    /// Cells with nothing at all on them.
+    pub fn free_cells(&self) -> &[(i32, i32)] {
+        self.grid.free_cells()
+    }
+
+    /// The cell next to `(x, y)` towards `dir`, wrapped when the board wraps.
+    pub fn next_cell(&self, x: i32, y: i32, dir: Direction) -> (i32, i32) {
+        let next = match dir {
+            Direction::Up => (x, y - 1),
+            Direction::Down => (x, y + 1),
+            Direction::Left => (x - 1, y),
+            Direction::Right => (x + 1, y),
+        };
+        match self.wrap_bounds() {
+            Some(bounds) => bounds.wrap(next.0, next.1),
+            None => next,
         }
+    }
 
-        self.update_snake(dir);
+    /// The round is over, `restart` starts the next one.
+    pub fn is_game_over(&self) -> bool {
+        self.game_over
     }
 
-    pub fn draw(&self, con: &Context, g: &mut G2d) {
-        self.snake.draw(con, g);
+    pub fn is_match_over(&self) -> bool {
+        self.match_over
+    }
 
-        if self.food_exists {
-            draw_block(FOOD_COLOR, self.food_x, self.food_y, con, g);
+    /// The game ended because the snakes filled the board.
+    pub fn is_won(&self) -> bool {
+        self.won
+    }
+
+    /// Steps played since the game or round started.
+    pub fn steps(&self) -> u32 {
+        self.steps
+    }
+
+    /// Steps left before the time limit, `None` without one.
+    pub fn steps_left(&self) -> Option<u32> {
+        self.config.time_limit.map(|limit| limit.saturating_sub(self.steps))
+    }
+
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            players: self.players.clone(),
+            wins: self.wins.clone(),
+            foods: self.foods.clone(),
+            level: self.level,
+            apples: self.apples,
+            game_over: self.game_over,
+            match_over: self.match_over,
+            won: self.won,
+            steps: self.steps,
+            rng: self.rng.clone(),
         }
+    }
 
-        draw_rectangle(BORDER_COLOR, 0, 0, self.width, 1, con, g);
-        draw_rectangle(BORDER_COLOR, 0, self.height - 1, self.width, 1, con, g);
-        draw_rectangle(BORDER_COLOR, 0, 0, 1, self.height, con, g);
-        draw_rectangle(BORDER_COLOR, self.width - 1, 0, 1, self.height, con, g);
+    /// Puts the game back the way it was when `snapshot` was taken.
+    pub fn restore(&mut self, snapshot: Snapshot) {
+        self.players = snapshot.players;
+        self.wins = snapshot.wins;
+        self.foods = snapshot.foods;
+        self.level = snapshot.level;
+        self.apples = snapshot.apples;
+        self.game_over = snapshot.game_over;
+        self.match_over = snapshot.match_over;
+        self.won = snapshot.won;
+        self.steps = snapshot.steps;
+        self.rng = snapshot.rng;
+        self.rebuild_grid();
+    }
 
+    /// Moves the first snake one cell, turning first if `dir` is given.
+    pub fn step(&mut self, dir: Option<Direction>) -> Vec<Event> {
+        self.step_all(&[dir])
+    }
+
+    /// Moves every snake one cell, `dirs[i]` steering player `i`.
+    ///
+    /// Turning back onto a snake's own neck is ignored, the snake keeps
+    /// going straight instead. Snakes move at the same time: one running
+    /// into another's body dies, two heads meeting on a cell or running
+    /// into each other both die. A snake that dies stays where it was, so
+    /// a snake following its tail dies as well.
+    pub fn step_all(&mut self, dirs: &[Option<Direction>]) -> Vec<Event> {
+        let mut events = Vec::new();
         if self.game_over {
-            draw_rectangle(GAMEOVER_COLOR, 0, 0, self.width, self.height, con, g);
+            return events;
+        }
+
+        self.tick_effects(&mut events);
+
+        let wrap = self.wrap_bounds();
+        let moves: Vec<(Option<Direction>, (i32, i32))> = self.players
+            .iter()
+            .enumerate()
+            .map(|(i, player)| {
+                let dir = match dirs.get(i).cloned().unwrap_or(None) {
+                    Some(d) if d == player.snake.head_direction().opposite() => None,
+                    d => d,
+                };
+                (dir, player.snake.next_head(dir, wrap))
+            })
+            .collect();
+
+        // snakes dying don't move out of the way, which can kill others in
+        // turn, so look again until nobody else dies
+        let mut moving: Vec<bool> = self.players.iter().map(|p| p.alive).collect();
+        loop {
+            let dying: Vec<usize> = (0..self.players.len())
+                .filter(|&i| moving[i] && !self.check_if_snake_alive(i, &moves, &moving))
+                .collect();
+            if dying.is_empty() {
+                break;
+            }
+            for i in dying {
+                moving[i] = false;
+                self.players[i].alive = false;
+                events.push(Event::Died { player: i });
+                if self.config.arena {
+                    self.leave_remains(i, &mut events);
+                }
+            }
+        }
+
+        let mut ate_apple = false;
+        for (i, &(dir, _)) in moves.iter().enumerate() {
+            if !self.players[i].alive {
+                continue;
+            }
+
+            let tail = self.players[i].snake.move_forward(dir, wrap);
+            self.grid.remove_snake(tail.x, tail.y);
+            let (head_x, head_y) = self.players[i].snake.head_position();
+            self.grid.add_snake(head_x, head_y);
+            events.push(Event::Moved {
+                player: i,
+                x: head_x,
+                y: head_y,
+            });
+
+            if let Some(kind) = self.check_eating(i, &mut events) {
+                ate_apple = ate_apple || kind == FoodKind::Apple;
+            }
+        }
+
+        if self.check_round_over(&mut events) {
+            return events;
+        }
+
+        self.steps += 1;
+        if self.steps_left() == Some(0) {
+            self.time_up(&mut events);
+            return events;
+        }
+
+        if ate_apple {
+            self.apples += 1;
+            let goal = self.level().apples;
+            if goal > 0 && self.apples >= goal && self.level + 1 < self.levels.len() {
+                let next = self.level + 1;
+                self.load_level(next);
+                events.push(Event::LevelUp { level: next + 1 });
+            }
         }
+
+        self.tick_foods(&mut events);
+
+        if self.food().is_none() {
+            match self.add_food() {
+                Some((x, y)) => events.push(Event::FoodSpawned {
+                    kind: FoodKind::Apple,
+                    x,
+                    y,
+                }),
+                // specials in the way go away in time, then the apple fits
+                None if self.foods.is_empty() => {
+                    self.fill_board(&mut events);
+                    return events;
+                }
+                None => {}
+            }
+        }
+        self.maybe_add_special(&mut events);
+
+        events
     }
 
-    pub fn update(&mut self, delta_time: f64) {
-        self.waiting_time += delta_time;
+    /// Starts the next round, or a new match once the last one was won.
+    pub fn restart(&mut self) {
+        if self.match_over {
+            for wins in &mut self.wins {
+                *wins = 0;
+            }
+            self.match_over = false;
+        }
+        self.load_level(0);
+        self.game_over = false;
+        self.won = false;
+        self.steps = 0;
+    }
 
-        if self.game_over {
-            if self.waiting_time > RESTART_TIME {
-                self.restart();
+    /// Ends the game as won, giving a versus round to the longest snake.
+    fn fill_board(&mut self, events: &mut Vec<Event>) {
+        self.game_over = true;
+        self.won = true;
+        events.push(Event::BoardFilled);
+        if !self.config.is_versus() {
+            return;
+        }
+
+        let longest = (0..self.players.len())
+            .filter(|&i| self.players[i].alive)
+            .map(|i| self.players[i].snake.len())
+            .max()
+            .unwrap_or(0);
+        let leaders: Vec<usize> = (0..self.players.len())
+            .filter(|&i| self.players[i].alive && self.players[i].snake.len() == longest)
+            .collect();
+        let winner = if leaders.len() == 1 { Some(leaders[0]) } else { None };
+        self.end_round(winner, events);
+    }
+
+    /// Clears the body of `player` off the board, leaving food on it.
+    fn leave_remains(&mut self, player: usize, events: &mut Vec<Event>) {
+        let cells: Vec<(i32, i32)> = self.players[player]
+            .snake
+            .blocks()
+            .map(|b| (b.x, b.y))
+            .collect();
+        for &(x, y) in &cells {
+            self.grid.remove_snake(x, y);
+        }
+        for (x, y) in cells {
+            if self.is_empty(x, y) {
+                self.put_food(Food::new(FoodKind::Remains, x, y));
+                events.push(Event::FoodSpawned {
+                    kind: FoodKind::Remains,
+                    x,
+                    y,
+                });
             }
+        }
+    }
+
+    /// Ends the game once time is up, giving a versus round to the highest
+    /// score.
+    fn time_up(&mut self, events: &mut Vec<Event>) {
+        self.game_over = true;
+        events.push(Event::TimeUp);
+        if !self.config.is_versus() {
             return;
         }
 
-        if !self.food_exists {
-            self.add_food();
+        let best = (0..self.players.len())
+            .filter(|&i| self.players[i].alive)
+            .map(|i| self.players[i].score)
+            .max()
+            .unwrap_or(0);
+        let leaders: Vec<usize> = (0..self.players.len())
+            .filter(|&i| self.players[i].alive && self.players[i].score == best)
+            .collect();
+        let winner = if leaders.len() == 1 { Some(leaders[0]) } else { None };
+        self.end_round(winner, events);
+    }
+
+    fn check_round_over(&mut self, events: &mut Vec<Event>) -> bool {
+        let alive: Vec<usize> = (0..self.players.len())
+            .filter(|&i| self.players[i].alive)
+            .collect();
+
+        if !self.config.is_versus() {
+            self.game_over = alive.is_empty();
+            return self.game_over;
+        }
+        if alive.len() > 1 {
+            return false;
+        }
+
+        self.game_over = true;
+        self.end_round(alive.first().cloned(), events);
+        true
+    }
+
+    fn end_round(&mut self, winner: Option<usize>, events: &mut Vec<Event>) {
+        events.push(Event::RoundOver { winner });
+        if let Some(w) = winner {
+            self.wins[w] += 1;
+            if self.wins[w] >= self.config.rounds_to_win {
+                self.match_over = true;
+                events.push(Event::MatchOver { winner: w });
+            }
         }
+    }
+
+    fn load_level(&mut self, index: usize) {
+        let levels = self.levels.clone();
+        let level = &levels[index];
 
-        if self.waiting_time > MOVING_PERIOD {
-            self.update_snake(None);
+        let scores: Vec<u32> = (0..self.config.players)
+            .map(|i| if index == 0 { 0 } else { self.players[i].score })
+            .collect();
+
+        self.level = index;
+        self.apples = 0;
+        self.players = (0..self.config.players)
+            .map(|i| {
+                let (body, direction) = level.spawn(i, self.config.players);
+                Player {
+                    snake: Snake::spawn(&body, direction),
+                    alive: true,
+                    score: scores[i],
+                    effects: Vec::new(),
+                }
+            })
+            .collect();
+        self.foods.clear();
+        self.rebuild_grid();
+        for &(x, y) in &level.food {
+            self.put_food(Food::new(FoodKind::Apple, x, y));
+        }
+        if self.foods.is_empty() {
+            self.add_food();
         }
     }
 
-    fn check_eating(&mut self) {
-        let (head_x, head_y): (i32, i32) = self.snake.head_position();
-        if self.food_exists && self.food_x == head_x && self.food_y == head_y {
-            self.food_exists = false;
-            self.snake.restore_tail();
+    /// Fills the grid again from the level, the snakes and the food.
+    fn rebuild_grid(&mut self) {
+        self.grid = Grid::new(self.config.width, self.config.height, self.bounds());
+        for &(x, y) in &self.levels[self.level].walls {
+            self.grid.set_wall(x, y, true);
+        }
+        for player in &self.players {
+            // the arena clears dead snakes away
+            if !player.alive && self.config.arena {
+                continue;
+            }
+            for block in player.snake.blocks() {
+                self.grid.add_snake(block.x, block.y);
+            }
+        }
+        for food in &self.foods {
+            self.grid.set_food(food.x, food.y, true);
         }
     }
 
-    fn check_if_snake_alive(&self, dir: Option<Direction>) -> bool {
-        let (next_x, next_y) = self.snake.next_head(dir);
+    /// Eats whatever is under the head of `player`, returning what it was.
+    fn check_eating(&mut self, player: usize, events: &mut Vec<Event>) -> Option<FoodKind> {
+        let (head_x, head_y): (i32, i32) = self.players[player].snake.head_position();
+        let food = self.foods.remove(self.food_at(head_x, head_y)?);
+        self.grid.set_food(food.x, food.y, false);
+        let kind = food.kind;
 
-        if self.snake.overlap_tail(next_x, next_y) {
-            return false;
+        events.push(Event::Ate {
+            player,
+            kind,
+            x: head_x,
+            y: head_y,
+        });
+        self.players[player].score += kind.points() * self.level_number() as u32;
+
+        if kind.grows() {
+            let tail = self.players[player].snake.restore_tail();
+            self.grid.add_snake(tail.x, tail.y);
+            events.push(Event::Grew {
+                player,
+                length: self.players[player].snake.len(),
+            });
         }
 
-        next_x > 0 && next_y > 0 && next_x < self.width - 1 && next_y < self.height - 1
+        if kind == FoodKind::Shrink {
+            for block in self.players[player].snake.shrink(SHRINK_BLOCKS) {
+                self.grid.remove_snake(block.x, block.y);
+            }
+            events.push(Event::Shrank {
+                player,
+                length: self.players[player].snake.len(),
+            });
+        }
+
+        if let Some(effect) = kind.effect() {
+            let effects = &mut self.players[player].effects;
+            effects.retain(|e| e.effect != effect);
+            // slow and fast cancel each other out
+            effects.retain(|e| {
+                !matches!(
+                    (e.effect, effect),
+                    (Effect::Slow, Effect::Fast) | (Effect::Fast, Effect::Slow)
+                )
+            });
+            effects.push(ActiveEffect {
+                effect,
+                steps_left: effect.duration(),
+            });
+            events.push(Event::EffectStarted { player, effect });
+        }
+
+        Some(kind)
     }
 
-    fn add_food(&mut self) {
-        let mut rng = thread_rng();
+    fn tick_effects(&mut self, events: &mut Vec<Event>) {
+        for (i, player) in self.players.iter_mut().enumerate() {
+            for active in &mut player.effects {
+                active.steps_left = active.steps_left.saturating_sub(1);
+                if active.steps_left == 0 {
+                    events.push(Event::EffectEnded {
+                        player: i,
+                        effect: active.effect,
+                    });
+                }
+            }
+            player.effects.retain(|e| e.steps_left > 0);
+        }
+    }
 
-        let mut new_x = rng.gen_range(1, self.width - 1);
-        let mut new_y = rng.gen_range(1, self.height - 1);
-        while self.snake.overlap_tail(new_x, new_y) {
-            new_x = rng.gen_range(1, self.width - 1);
-            new_y = rng.gen_range(1, self.height - 1);
+    fn tick_foods(&mut self, events: &mut Vec<Event>) {
+        for food in &mut self.foods {
+            if let Some(ref mut steps) = food.steps_left {
+                *steps = steps.saturating_sub(1);
+                if *steps == 0 {
+                    events.push(Event::FoodExpired {
+                        kind: food.kind,
+                        x: food.x,
+                        y: food.y,
+                    });
+                    self.grid.set_food(food.x, food.y, false);
+                }
+            }
         }
+        self.foods.retain(|f| f.steps_left != Some(0));
+    }
 
-        self.food_x = new_x;
-        self.food_y = new_y;
-        self.food_exists = true;
+    fn maybe_add_special(&mut self, events: &mut Vec<Event>) {
+        let specials = self.foods.iter().filter(|f| f.kind != FoodKind::Apple).count();
+        if specials >= MAX_SPECIALS || !self.rng.gen_weighted_bool(SPECIAL_CHANCE) {
+            return;
+        }
+
+        let kind = *self.rng.choose(&FoodKind::specials()).unwrap();
+        if let Some((x, y)) = self.random_free_cell() {
+            self.put_food(Food::new(kind, x, y));
+            events.push(Event::FoodSpawned { kind, x, y });
+        }
     }
 
-    fn update_snake(&mut self, dir: Option<Direction>) {
-        if self.check_if_snake_alive(dir) {
-            self.snake.move_forward(dir);
-            self.check_eating();
+    fn wrap_bounds(&self) -> Option<Bounds> {
+        if self.config.wrap {
+            Some(self.bounds())
         } else {
-            self.game_over = true;
+            None
         }
-        self.waiting_time = 0.0;
     }
 
-    fn restart(&mut self) {
-        self.snake = Snake::new(2, 2);
-        self.waiting_time = 0.0;
-        self.food_exists = true;
-        self.food_x = 6;
-        self.food_y = 4;
-        self.game_over = false;
+    /// Whether `player` survives its move, given which snakes move at all.
+    fn check_if_snake_alive(
+        &self,
+        player: usize,
+        moves: &[(Option<Direction>, (i32, i32))],
+        moving: &[bool],
+    ) -> bool {
+        let (next_x, next_y) = moves[player].1;
+
+        if self.is_wall(next_x, next_y) || !self.bounds().contains(next_x, next_y) {
+            return false;
+        }
+
+        let mut blocks = self.grid.snakes_at(next_x, next_y) as usize;
+        for (i, other) in self.players.iter().enumerate() {
+            if !moving[i] {
+                continue;
+            }
+            if i != player && moves[i].1 == (next_x, next_y) {
+                return false;
+            }
+            if i == player && self.has_effect(player, Effect::Ghost) {
+                blocks -= other.snake.blocks().filter(|b| (b.x, b.y) == (next_x, next_y)).count();
+                continue;
+            }
+            // a tail moves out of the way, unless that snake grows
+            let (x, y) = moves[i].1;
+            let grows = match self.food_at(x, y) {
+                Some(f) => self.foods[f].kind.grows(),
+                None => false,
+            };
+            if !grows && other.snake.tail_position() == (next_x, next_y) {
+                blocks -= 1;
+            }
+        }
+        blocks == 0
     }
-}
\ No newline at end of file
+
+    /// Free and without food on it, a spot for new food.
+    fn is_empty(&self, x: i32, y: i32) -> bool {
+        self.grid.is_empty(x, y)
+    }
+
+    fn put_food(&mut self, food: Food) {
+        self.grid.set_food(food.x, food.y, true);
+        self.foods.push(food);
+    }
+
+    /// Puts a new apple on the board, returning where. `None` when the
+    /// board has no room left.
+    fn add_food(&mut self) -> Option<(i32, i32)> {
+        let zones: Vec<(i32, i32)> = self.level()
+            .food_zones
+            .iter()
+            .cloned()
+            .filter(|&(x, y)| self.is_empty(x, y))
+            .collect();
+        let (x, y) = match self.rng.choose(&zones) {
+            Some(&cell) => cell,
+            None => self.random_free_cell()?,
+        };
+        self.put_food(Food::new(FoodKind::Apple, x, y));
+        Some((x, y))
+    }
+
+    /// Any empty cell, picked by the rng from the cells in row order so the
+    /// same board and rng always give the same cell.
+    fn random_free_cell(&mut self) -> Option<(i32, i32)> {
+        let count = self.grid.free_cells().len();
+        if count == 0 {
+            return None;
+        }
+        let n = self.rng.gen_range(0, count);
+        self.grid.nth_free(n)
+    }
+}
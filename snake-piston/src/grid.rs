@@ -0,0 +1,178 @@
+//! What is on each cell of the board.
+//!
+//! The game keeps a `Grid` next to its snakes so that checking a cell never
+//! has to walk a snake body, and so new food can be picked straight from
+//! the cells with nothing on them.
+
+use snake::Bounds;
+
+/// Marks `Grid::free_index` entries of cells that are not free.
+const NOT_FREE: usize = usize::MAX;
+
+#[derive(Debug, Clone)]
+pub struct Grid {
+    width: i32,
+    height: i32,
+    /// Cells food may go on, the rest is border.
+    bounds: Bounds,
+    /// Snake blocks on each cell. A ghost crossing its own body puts two
+    /// blocks on one cell.
+    snakes: Vec<u8>,
+    walls: Vec<bool>,
+    food: Vec<bool>,
+    /// Cells inside `bounds` with nothing on them, in no particular order.
+    free: Vec<(i32, i32)>,
+    /// Where each cell is in `free`, `NOT_FREE` when it isn't.
+    free_index: Vec<usize>,
+    /// Free cells counted in a Fenwick tree over the cells in row order, so
+    /// `nth_free` doesn't depend on the order of `free`.
+    free_counts: Vec<usize>,
+}
+
+impl Grid {
+    /// A `width` by `height` board, empty inside `bounds`.
+    pub fn new(width: i32, height: i32, bounds: Bounds) -> Grid {
+        let cells = (width * height) as usize;
+        let mut grid = Grid {
+            width,
+            height,
+            bounds,
+            snakes: vec![0; cells],
+            walls: vec![false; cells],
+            food: vec![false; cells],
+            free: Vec::with_capacity(cells),
+            free_index: vec![NOT_FREE; cells],
+            free_counts: vec![0; cells],
+        };
+        for y in bounds.top..=bounds.bottom {
+            for x in bounds.left..=bounds.right {
+                grid.update(x, y);
+            }
+        }
+        grid
+    }
+
+    fn index(&self, x: i32, y: i32) -> Option<usize> {
+        if x < 0 || y < 0 || x >= self.width || y >= self.height {
+            return None;
+        }
+        Some((y * self.width + x) as usize)
+    }
+
+    pub fn snakes_at(&self, x: i32, y: i32) -> u8 {
+        match self.index(x, y) {
+            Some(i) => self.snakes[i],
+            None => 0,
+        }
+    }
+
+    pub fn has_snake(&self, x: i32, y: i32) -> bool {
+        self.snakes_at(x, y) > 0
+    }
+
+    pub fn is_wall(&self, x: i32, y: i32) -> bool {
+        matches!(self.index(x, y), Some(i) if self.walls[i])
+    }
+
+    pub fn has_food(&self, x: i32, y: i32) -> bool {
+        matches!(self.index(x, y), Some(i) if self.food[i])
+    }
+
+    /// Inside the border with no wall, snake or food on it.
+    pub fn is_empty(&self, x: i32, y: i32) -> bool {
+        matches!(self.index(x, y), Some(i) if self.free_index[i] != NOT_FREE)
+    }
+
+    /// Every empty cell, in no particular order.
+    pub fn free_cells(&self) -> &[(i32, i32)] {
+        &self.free
+    }
+
+    /// The empty cell numbered `n`, counting row by row from the top left,
+    /// so it only depends on what is on the board.
+    pub fn nth_free(&self, n: usize) -> Option<(i32, i32)> {
+        if n >= self.free.len() {
+            return None;
+        }
+        // walk down the tree to the last cell with at most `n` free before it
+        let cells = self.free_counts.len();
+        let mut step = cells.next_power_of_two();
+        let (mut index, mut left) = (0, n);
+        while step > 0 {
+            let next = index + step;
+            if next <= cells && self.free_counts[next - 1] <= left {
+                index = next;
+                left -= self.free_counts[next - 1];
+            }
+            step /= 2;
+        }
+        let width = self.width as usize;
+        Some(((index % width) as i32, (index / width) as i32))
+    }
+
+    pub fn add_snake(&mut self, x: i32, y: i32) {
+        if let Some(i) = self.index(x, y) {
+            self.snakes[i] += 1;
+            self.update(x, y);
+        }
+    }
+
+    pub fn remove_snake(&mut self, x: i32, y: i32) {
+        if let Some(i) = self.index(x, y) {
+            self.snakes[i] = self.snakes[i].saturating_sub(1);
+            self.update(x, y);
+        }
+    }
+
+    pub fn set_wall(&mut self, x: i32, y: i32, wall: bool) {
+        if let Some(i) = self.index(x, y) {
+            self.walls[i] = wall;
+            self.update(x, y);
+        }
+    }
+
+    pub fn set_food(&mut self, x: i32, y: i32, food: bool) {
+        if let Some(i) = self.index(x, y) {
+            self.food[i] = food;
+            self.update(x, y);
+        }
+    }
+
+    /// Adds the cell to the free list or takes it out, as needed.
+    fn update(&mut self, x: i32, y: i32) {
+        let i = match self.index(x, y) {
+            Some(i) => i,
+            None => return,
+        };
+        let empty = self.bounds.contains(x, y) && self.snakes[i] == 0 && !self.walls[i] && !self.food[i];
+        let listed = self.free_index[i] != NOT_FREE;
+
+        if empty && !listed {
+            self.free_index[i] = self.free.len();
+            self.free.push((x, y));
+            self.count_free(i, true);
+        } else if !empty && listed {
+            let at = self.free_index[i];
+            self.free.swap_remove(at);
+            if let Some(&(moved_x, moved_y)) = self.free.get(at) {
+                let moved = self.index(moved_x, moved_y).unwrap();
+                self.free_index[moved] = at;
+            }
+            self.free_index[i] = NOT_FREE;
+            self.count_free(i, false);
+        }
+    }
+
+    /// Counts cell `i` in `free_counts` as free or not any more.
+    fn count_free(&mut self, i: usize, free: bool) {
+        let mut node = i + 1;
+        while node <= self.free_counts.len() {
+            if free {
+                self.free_counts[node - 1] += 1;
+            } else {
+                self.free_counts[node - 1] -= 1;
+            }
+            node += node & node.wrapping_neg();
+        }
+    }
+}
@@ -0,0 +1,42 @@
+use std::collections::VecDeque;
+
+use snake::Direction;
+
+/// Most turns kept waiting at once, further key presses are dropped.
+const QUEUE_SIZE: usize = 3;
+
+/// Turns typed faster than the snake moves, played back one per step.
+///
+/// Each turn is checked against the direction the snake will have after
+/// the turns already waiting, so Up then Left within one step makes two
+/// turns instead of losing one or folding the snake back on itself.
+#[derive(Debug, Clone, Default)]
+pub struct InputQueue {
+    turns: VecDeque<Direction>,
+}
+
+impl InputQueue {
+    pub fn new() -> InputQueue {
+        InputQueue::default()
+    }
+
+    /// Queues `dir` for a snake currently heading `heading`. Returns false
+    /// when it is dropped: no change of direction, a reversal, or a full queue.
+    pub fn push(&mut self, dir: Direction, heading: Direction) -> bool {
+        let last = self.turns.back().cloned().unwrap_or(heading);
+        if dir == last || dir == last.opposite() || self.turns.len() >= QUEUE_SIZE {
+            return false;
+        }
+        self.turns.push_back(dir);
+        true
+    }
+
+    /// The turn to make on this step.
+    pub fn pop(&mut self) -> Option<Direction> {
+        self.turns.pop_front()
+    }
+
+    pub fn clear(&mut self) {
+        self.turns.clear();
+    }
+}
@@ -0,0 +1,427 @@
+//! Plain text level maps.
+//!
+//! A level file has a few `key: value` lines followed by the map itself,
+//! one character per cell:
+//!
+//! ```text
+//! ; lines starting with ';' are comments
+//! name: Pillars
+//! apples: 8
+//! direction: right
+//! length: 3
+//! ..........
+//! .@...##...
+//! .....##.**
+//! ........**
+//! ```
+//!
+//! `.` is floor, `#` a wall, `@` the head of the snake at the start, `*`
+//! a cell where food may spawn (anywhere free when there are none) and `o`
+//! food that is already there when the level starts. The map only covers
+//! the playfield, the border around it is added by the game.
+
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::Path;
+
+use snake::Direction;
+
+/// What a cell of the map holds, besides the start.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Cell {
+    Floor,
+    Wall,
+    /// Food may spawn here, `*` in the file.
+    FoodZone,
+    /// Food is here when the level starts, `o` in the file.
+    Food,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Level {
+    pub name: String,
+    /// Board size, border included.
+    pub width: i32,
+    pub height: i32,
+    pub walls: Vec<(i32, i32)>,
+    pub food_zones: Vec<(i32, i32)>,
+    pub food: Vec<(i32, i32)>,
+    pub start: (i32, i32),
+    pub direction: Direction,
+    pub length: usize,
+    /// Apples to eat before moving on to the next level, 0 to stay here.
+    pub apples: u32,
+}
+
+impl Level {
+    /// The classic empty board. The first apple is left out when the board
+    /// is too small for it, the game then puts one anywhere free.
+    pub fn open(width: i32, height: i32) -> Level {
+        let mut level = Level {
+            name: String::from("Classic"),
+            width,
+            height,
+            walls: Vec::new(),
+            food_zones: Vec::new(),
+            food: Vec::new(),
+            start: (4, 2),
+            direction: Direction::Right,
+            length: 3,
+            apples: 0,
+        };
+        if level.is_inside(6, 4) {
+            level.food.push((6, 4));
+        }
+        level
+    }
+
+    /// The classic empty board with a snake `length` blocks long, moved
+    /// right far enough for it to fit.
+    pub fn open_with_length(width: i32, height: i32, length: usize) -> Result<Level, String> {
+        let mut level = Level::open(width, height);
+        level.start.0 = level.start.0.max(length as i32);
+        level.with_length(length)
+    }
+
+    /// The same level with snakes starting `length` blocks long.
+    pub fn with_length(mut self, length: usize) -> Result<Level, String> {
+        self.length = length;
+        self.check()?;
+        Ok(self)
+    }
+
+    pub fn parse(text: &str) -> Result<Level, String> {
+        let mut name = String::from("Unnamed");
+        let mut direction = Direction::Right;
+        let mut length = 3;
+        let mut apples = 0;
+        let mut rows: Vec<(usize, &str)> = Vec::new();
+
+        for (number, line) in text.lines().enumerate() {
+            let number = number + 1;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with(';') {
+                continue;
+            }
+
+            if let Some(colon) = line.find(':') {
+                if !rows.is_empty() {
+                    return Err(format!("line {}: settings must come before the map", number));
+                }
+                let key = line[..colon].trim();
+                let value = line[colon + 1..].trim();
+                match key {
+                    "name" => name = value.to_string(),
+                    "direction" => {
+                        direction = parse_direction(value)
+                            .ok_or_else(|| format!("line {}: unknown direction '{}'", number, value))?
+                    }
+                    "length" => length = parse_number(value, number)?,
+                    "apples" => apples = parse_number(value, number)?,
+                    _ => return Err(format!("line {}: unknown setting '{}'", number, key)),
+                }
+            } else {
+                rows.push((number, line));
+            }
+        }
+
+        if rows.is_empty() {
+            return Err(String::from("the level has no map"));
+        }
+
+        let columns = rows[0].1.chars().count();
+        let mut walls = Vec::new();
+        let mut food_zones = Vec::new();
+        let mut food = Vec::new();
+        let mut start = None;
+
+        for (y, &(number, row)) in rows.iter().enumerate() {
+            if row.chars().count() != columns {
+                return Err(format!("line {}: map rows must all have the same length", number));
+            }
+            for (x, cell) in row.chars().enumerate() {
+                // the border takes the first row and column of the board
+                let pos = (x as i32 + 1, y as i32 + 1);
+                match cell {
+                    '.' => {}
+                    '#' => walls.push(pos),
+                    '*' => food_zones.push(pos),
+                    'o' => food.push(pos),
+                    '@' => {
+                        if start.is_some() {
+                            return Err(format!("line {}: more than one start '@'", number));
+                        }
+                        start = Some(pos);
+                    }
+                    _ => return Err(format!("line {}: unknown map cell '{}'", number, cell)),
+                }
+            }
+        }
+
+        let start = start.ok_or_else(|| String::from("the map has no start '@'"))?;
+
+        let level = Level {
+            name,
+            width: columns as i32 + 2,
+            height: rows.len() as i32 + 2,
+            walls,
+            food_zones,
+            food,
+            start,
+            direction,
+            length,
+            apples,
+        };
+        level.check()?;
+        Ok(level)
+    }
+
+    pub fn load(path: &Path) -> Result<Level, String> {
+        let mut text = String::new();
+        File::open(path)
+            .and_then(|mut file| file.read_to_string(&mut text))
+            .map_err(|e| format!("{}: {}", path.display(), e))?;
+        Level::parse(&text).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    /// The level in the file format, `parse` reads it back unchanged.
+    pub fn to_text(&self) -> String {
+        let mut text = format!(
+            "name: {}\napples: {}\ndirection: {}\nlength: {}\n",
+            self.name,
+            self.apples,
+            direction_name(self.direction),
+            self.length
+        );
+        for y in 1..self.height - 1 {
+            for x in 1..self.width - 1 {
+                let cell = if self.is_wall(x, y) {
+                    '#'
+                } else if self.start == (x, y) {
+                    '@'
+                } else if self.food.contains(&(x, y)) {
+                    'o'
+                } else if self.food_zones.contains(&(x, y)) {
+                    '*'
+                } else {
+                    '.'
+                };
+                text.push(cell);
+            }
+            text.push('\n');
+        }
+        text
+    }
+
+    pub fn is_wall(&self, x: i32, y: i32) -> bool {
+        self.walls.contains(&(x, y))
+    }
+
+    /// Whether `(x, y)` is on the map, inside the border.
+    pub fn is_inside(&self, x: i32, y: i32) -> bool {
+        x > 0 && y > 0 && x < self.width - 1 && y < self.height - 1
+    }
+
+    pub fn cell(&self, x: i32, y: i32) -> Cell {
+        if self.is_wall(x, y) {
+            Cell::Wall
+        } else if self.food.contains(&(x, y)) {
+            Cell::Food
+        } else if self.food_zones.contains(&(x, y)) {
+            Cell::FoodZone
+        } else {
+            Cell::Floor
+        }
+    }
+
+    /// Makes `(x, y)` hold `cell` and nothing else. Cells outside the map
+    /// are left alone.
+    pub fn set_cell(&mut self, x: i32, y: i32, cell: Cell) {
+        if !self.is_inside(x, y) {
+            return;
+        }
+        self.walls.retain(|&c| c != (x, y));
+        self.food_zones.retain(|&c| c != (x, y));
+        self.food.retain(|&c| c != (x, y));
+        match cell {
+            Cell::Floor => {}
+            Cell::Wall => self.walls.push((x, y)),
+            Cell::FoodZone => self.food_zones.push((x, y)),
+            Cell::Food => self.food.push((x, y)),
+        }
+    }
+
+    /// Moves the start to `start` heading `direction`, as long as the snake
+    /// still fits there.
+    pub fn set_start(&mut self, start: (i32, i32), direction: Direction) -> Result<(), String> {
+        let old = (self.start, self.direction);
+        self.start = start;
+        self.direction = direction;
+        let fits = self.check();
+        if fits.is_err() {
+            self.start = old.0;
+            self.direction = old.1;
+        }
+        fits
+    }
+
+    /// Checks the level can be played: the snake fits at its start and food
+    /// is on the map, off the walls and not on top of the snake.
+    pub fn check(&self) -> Result<(), String> {
+        self.check_start()?;
+        if self.food.iter().any(|&(x, y)| !self.is_inside(x, y) || self.is_wall(x, y)) {
+            return Err(String::from("there is food off the map or in a wall"));
+        }
+        if self.start_body().iter().any(|cell| self.food.contains(cell)) {
+            return Err(String::from("there is food on the snake at its start"));
+        }
+        Ok(())
+    }
+
+    /// Checks every snake of a game of `players` fits where `spawn` puts
+    /// it: inside the board, off the walls and the food, and clear of the
+    /// others. `check` only looks at the first.
+    pub fn check_spawns(&self, players: usize) -> Result<(), String> {
+        let mut taken: Vec<(i32, i32)> = Vec::new();
+        for player in 0..players {
+            let (body, _) = self.spawn(player, players);
+            for &(x, y) in &body {
+                if !self.is_inside(x, y) || self.is_wall(x, y) {
+                    return Err(format!("snake {} does not fit at its start", player + 1));
+                }
+                if self.food.contains(&(x, y)) {
+                    return Err(format!("there is food on snake {} at its start", player + 1));
+                }
+                if taken.contains(&(x, y)) {
+                    return Err(format!("snake {} starts on top of another one", player + 1));
+                }
+            }
+            taken.extend(body);
+        }
+        Ok(())
+    }
+
+    /// Cells the snake takes up when the level starts, head first.
+    pub fn start_body(&self) -> Vec<(i32, i32)> {
+        self.body_from(self.start, self.direction)
+    }
+
+    /// A snake of the level's length with its head on `head`, lying
+    /// straight behind it.
+    fn body_from(&self, head: (i32, i32), direction: Direction) -> Vec<(i32, i32)> {
+        let back = direction.opposite();
+        let mut cell = head;
+        let mut body = vec![cell];
+        for _ in 1..self.length {
+            cell = match back {
+                Direction::Up => (cell.0, cell.1 - 1),
+                Direction::Down => (cell.0, cell.1 + 1),
+                Direction::Left => (cell.0 - 1, cell.1),
+                Direction::Right => (cell.0 + 1, cell.1),
+            };
+            body.push(cell);
+        }
+        body
+    }
+
+    /// Where `player` of `players` starts and which way it heads.
+    ///
+    /// The first player starts at `@`, the second one at the same spot
+    /// turned half way around the board, facing the other way. With more
+    /// than two players each gets a row of its own instead, spread down the
+    /// board, starting on the left heading right or on the right heading
+    /// left in turn. Those rows are meant for the open board, they don't
+    /// look out for walls or each other: see `check_spawns`.
+    pub fn spawn(&self, player: usize, players: usize) -> (Vec<(i32, i32)>, Direction) {
+        if players > 2 {
+            let (rows, players) = (self.height - 2, players as i32);
+            let y = 1 + (player as i32 * rows + rows / 2) / players;
+            let length = self.length as i32;
+            let (head, direction) = match player % 2 {
+                0 => ((length, y), Direction::Right),
+                _ => ((self.width - 1 - length, y), Direction::Left),
+            };
+            return (self.body_from(head, direction), direction);
+        }
+
+        let body = self.start_body();
+        if player == 0 {
+            return (body, self.direction);
+        }
+        let mirrored = body.iter()
+            .map(|&(x, y)| (self.width - 1 - x, self.height - 1 - y))
+            .collect();
+        (mirrored, self.direction.opposite())
+    }
+
+    fn check_start(&self) -> Result<(), String> {
+        if self.length == 0 {
+            return Err(String::from("the snake needs a length of at least 1"));
+        }
+        for (x, y) in self.start_body() {
+            if !self.is_inside(x, y) || self.is_wall(x, y) {
+                return Err(String::from("the snake does not fit at its start"));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Loads every `.txt` level in `dir`, in file name order.
+///
+/// All levels of a sequence share one board size, the size of the first.
+pub fn load_sequence(dir: &Path) -> Result<Vec<Level>, String> {
+    let mut paths = Vec::new();
+    let entries = fs::read_dir(dir).map_err(|e| format!("{}: {}", dir.display(), e))?;
+    for entry in entries {
+        let path = entry.map_err(|e| format!("{}: {}", dir.display(), e))?.path();
+        if path.extension().and_then(|ext| ext.to_str()) == Some("txt") {
+            paths.push(path);
+        }
+    }
+    paths.sort();
+
+    let mut levels: Vec<Level> = Vec::new();
+    for path in paths {
+        let level = Level::load(&path)?;
+        if let Some(first) = levels.first() {
+            if (level.width, level.height) != (first.width, first.height) {
+                return Err(format!(
+                    "{}: board is {}x{} but the first level is {}x{}",
+                    path.display(),
+                    level.width,
+                    level.height,
+                    first.width,
+                    first.height
+                ));
+            }
+        }
+        levels.push(level);
+    }
+    Ok(levels)
+}
+
+pub fn parse_direction(text: &str) -> Option<Direction> {
+    match text {
+        "up" => Some(Direction::Up),
+        "down" => Some(Direction::Down),
+        "left" => Some(Direction::Left),
+        "right" => Some(Direction::Right),
+        _ => None,
+    }
+}
+
+pub fn direction_name(dir: Direction) -> &'static str {
+    match dir {
+        Direction::Up => "up",
+        Direction::Down => "down",
+        Direction::Left => "left",
+        Direction::Right => "right",
+    }
+}
+
+fn parse_number<T: ::std::str::FromStr>(value: &str, number: usize) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("line {}: '{}' is not a number", number, value))
+}
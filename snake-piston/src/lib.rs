@@ -0,0 +1,21 @@
+//! Snake rules that run without a window.
+//!
+//! The piston frontend in `main.rs` only draws these types and feeds them
+//! key presses, so the same game can be stepped from tests or scripts.
+
+extern crate rand;
+
+pub mod ai;
+pub mod daily;
+pub mod food;
+pub mod env;
+pub mod game;
+pub mod grid;
+pub mod input;
+pub mod level;
+pub mod options;
+pub mod replay;
+pub mod rewind;
+pub mod snake;
+pub mod storage;
+pub mod theme;
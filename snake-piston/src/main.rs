@@ -1,41 +1,244 @@
 extern crate piston_window;
 extern crate rand;
+extern crate rusty_snake;
 
+mod app;
 mod draw;
-mod snake;
-mod game;
+mod editor;
+mod skin;
+
+use std::env;
+use std::path::{Path, PathBuf};
+use std::process;
 
 use piston_window::*;
-use piston_window::types::Color;
 
-use game::Game;
-use draw::to_coord_u32;
+use rusty_snake::daily::{self, Challenge};
+use rusty_snake::game::GameConfig;
+use rusty_snake::level::{self, Level};
+use rusty_snake::options::{self, Options};
+use rusty_snake::replay::Replay;
+use rusty_snake::storage;
+use rusty_snake::theme::{self, Theme};
+
+use app::App;
+use editor::Editor;
+use skin::Themes;
+
+fn resources_dir() -> PathBuf {
+    match env::var("CARGO_MANIFEST_DIR") {
+        Ok(manifest_dir) => PathBuf::from(manifest_dir).join("resources"),
+        Err(_) => PathBuf::from("resources"),
+    }
+}
+
+/// Defaults, then the config file, then the command line.
+fn load_options() -> Result<Options, String> {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let flags = Options::parse_args(args.clone())?;
+
+    let config = match flags.config {
+        Some(path) => Some(path),
+        None => storage::config_file().filter(|path| path.exists()),
+    };
+    let options = match config {
+        Some(path) => Options::default().read_config(&path)?,
+        None => Options::default(),
+    };
+    let options = options.apply_args(args)?;
+
+    options.validate()?;
+    Ok(options)
+}
+
+/// The theme files, or the classic colors alone when they can't be read.
+/// The theme asked for must be one of them.
+fn load_themes(options: &Options) -> Result<Vec<Theme>, String> {
+    let themes = theme::load_themes(&resources_dir().join("themes")).unwrap_or_else(|e| {
+        println!("Could not load themes, using the classic colors. {}", e);
+        vec![Theme::classic()]
+    });
+    if !themes.iter().any(|theme| theme.name == options.theme) {
+        let names: Vec<&str> = themes.iter().map(|theme| theme.name.as_str()).collect();
+        return Err(format!(
+            "unknown theme '{}', try one of: {}",
+            options.theme,
+            names.join(", ")
+        ));
+    }
+    Ok(themes)
+}
+
+/// The level files, or the open board when a size was asked for or the
+/// files can't be read. The snakes get the asked for length.
+fn load_levels(options: &Options) -> Result<Vec<Level>, String> {
+    let mut levels = Vec::new();
+    if options.uses_levels() {
+        match level::load_sequence(&resources_dir().join("levels")) {
+            Ok(loaded) => levels = loaded,
+            Err(e) => println!("Could not load levels, playing the classic board. {}", e),
+        }
+    }
+
+    if levels.is_empty() {
+        let (width, height) = options.board_size();
+        let length = options.length.unwrap_or(3);
+        return Ok(vec![Level::open_with_length(width, height, length)?]);
+    }
+    match options.length {
+        Some(length) => levels
+            .into_iter()
+            .map(|level| {
+                let name = level.name.clone();
+                level.with_length(length).map_err(|e| format!("level {}: {}", name, e))
+            })
+            .collect(),
+        None => Ok(levels),
+    }
+}
+
+/// `levels`, if every snake of a versus or arena game fits on each of
+/// them.
+fn check_spawns(levels: Vec<Level>, players: usize) -> Result<Vec<Level>, String> {
+    for level in &levels {
+        level.check_spawns(players).map_err(|e| format!("level {}: {}", level.name, e))?;
+    }
+    Ok(levels)
+}
+
+fn load_glyphs(window: &PistonWindow) -> Glyphs {
+    let font = resources_dir().join("DejaVuSerif.ttf");
+    Glyphs::new(font, window.factory.clone(), TextureSettings::new()).unwrap()
+}
+
+/// Runs the level editor on `path` until it is closed.
+fn edit(path: &Path, options: &Options, themes: Vec<Theme>) {
+    let mut editor = Editor::open(path, options).unwrap_or_else(|e| {
+        println!("Could not open the level. {}", e);
+        process::exit(1);
+    });
+
+    // Esc leaves a level being tried out before it closes the editor
+    let mut window: PistonWindow = WindowSettings::new("Snake level editor", editor.window_size())
+        .exit_on_esc(false)
+        .build()
+        .unwrap();
+    let mut glyphs = load_glyphs(&window);
+    editor.set_themes(Themes::load(themes, &options.theme, &mut window.factory));
 
-const BACK_COLOR: Color = [0.5, 0.5, 0.5, 1.0];
+    while let Some(event) = window.next() {
+        match event.press_args() {
+            Some(Button::Keyboard(key)) if !editor.key_pressed(key) => break,
+            Some(Button::Mouse(button)) => editor.mouse_pressed(button),
+            _ => {}
+        }
+        match event.release_args() {
+            Some(Button::Keyboard(key)) => editor.key_released(key),
+            Some(Button::Mouse(_)) => editor.mouse_released(),
+            _ => {}
+        }
+        if let Some(position) = event.mouse_cursor_args() {
+            editor.mouse_moved(position);
+        }
+        window.draw_2d(&event, |c, g| {
+            clear(editor.theme().background, g);
+            editor.draw(&c, g, &mut glyphs);
+        });
 
+        event.update(|arg| {
+            editor.update(arg.dt);
+        });
+    }
+}
 
+/// Checks a daily challenge run and prints its score.
+fn verify(path: &Path) {
+    let checked = Replay::load(path).and_then(|replay| daily::verify(&replay));
+    match checked {
+        Ok((date, score)) => println!("{}: a valid run of the {} challenge, score {}", path.display(), date, score),
+        Err(e) => {
+            println!("{}: not a valid run. {}", path.display(), e);
+            process::exit(1);
+        }
+    }
+}
 
 fn main() {
-    let (width, height) = (40, 30);
+    let options = load_options().unwrap_or_else(|e| {
+        println!("{}\n\n{}", e, options::USAGE);
+        process::exit(2);
+    });
+    if options.help {
+        println!("{}", options::USAGE);
+        return;
+    }
+    let themes = load_themes(&options).unwrap_or_else(|e| {
+        println!("{}\n\n{}", e, options::USAGE);
+        process::exit(2);
+    });
+    if let Some(ref path) = options.edit {
+        edit(path, &options, themes);
+        return;
+    }
+    if let Some(ref path) = options.verify {
+        verify(path);
+        return;
+    }
+
+    let (width, height) = options.board_size();
+    let mut config = match options.arena {
+        Some(snakes) => GameConfig::arena(width, height, snakes + 1),
+        None if options.cpu || options.versus => GameConfig::versus(width, height),
+        None => GameConfig::new(width, height),
+    };
+    config.wrap = options.wrap;
 
-    let mut window: PistonWindow =
-        WindowSettings::new("Snake", [to_coord_u32(width), to_coord_u32(height)])
-            .exit_on_esc(true)
-            .build()
-            .unwrap();
+    let mut app = match options.replay {
+        None if options.daily => App::daily(Challenge::today(), &options),
+        Some(ref file) => match Replay::load(file) {
+            Ok(replay) => App::watch(replay, &options),
+            Err(e) => {
+                println!("Could not load the replay. {}", e);
+                process::exit(1);
+            }
+        },
+        None => match load_levels(&options).and_then(|levels| check_spawns(levels, config.players)) {
+            Ok(levels) => App::new(config, levels, &options),
+            Err(e) => {
+                println!("{}", e);
+                process::exit(2);
+            }
+        },
+    };
+    if options.cpu || options.arena.is_some() {
+        app.add_cpu_opponents();
+    }
+
+    let mut window: PistonWindow = WindowSettings::new("Snake", app.window_size())
+        .exit_on_esc(true)
+        .build()
+        .unwrap();
+
+    let mut glyphs = load_glyphs(&window);
+    app.set_themes(Themes::load(themes, &options.theme, &mut window.factory));
 
-    let mut game = Game::new(width, height);
     while let Some(event) = window.next() {
         if let Some(Button::Keyboard(key)) = event.press_args() {
-            game.key_pressed(key);
+            app.key_pressed(key);
+        }
+        if let Some(Button::Keyboard(key)) = event.release_args() {
+            app.key_released(key);
+        }
+        if event.focus_args() == Some(false) {
+            app.pause();
         }
         window.draw_2d(&event, |c, g| {
-            clear(BACK_COLOR, g);
-            game.draw(&c, g);
+            clear(app.theme().background, g);
+            app.draw(&c, g, &mut glyphs);
         });
 
         event.update(|arg| {
-            game.update(arg.dt);
+            app.update(arg.dt);
         });
     }
-}
\ No newline at end of file
+}
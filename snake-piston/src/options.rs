@@ -0,0 +1,277 @@
+//! Settings from the command line and the config file.
+//!
+//! The config file uses the same `key: value` lines as the level files,
+//! with `;` starting a comment:
+//!
+//! ```text
+//! ; ~/.config/rusty-snake/config.txt
+//! width: 60
+//! height: 40
+//! cell_size: 16
+//! speed: 6
+//! length: 5
+//! wrap: true
+//! theme: dark
+//! smooth: true
+//! ```
+//!
+//! Flags on the command line win over the file.
+
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use game::MAX_PLAYERS;
+
+pub const USAGE: &str = "\
+Usage: rusty-snake [options]
+
+  --width N         board width in cells, border included (plays the open board)
+  --height N        board height in cells, border included (plays the open board)
+  --cell-size PX    size of a cell on screen in pixels
+  --speed N         steps per second at the start
+  --length N        blocks the snake starts with
+  --wrap            leave the board on one edge, come back on the other
+  --theme NAME      colors from resources/themes: classic, dark, nokia, high-contrast
+  --smooth          glide the snakes between cells instead of jumping
+  --practice        hold Backspace to take back steps (no replays or best scores)
+  --versus          two players on one keyboard
+  --cpu             versus against the computer
+  --arena N         you against N computer snakes on a bigger board, up to 7
+  --daily           today's challenge, the same board and food for everyone
+  --verify FILE     check a recorded daily challenge run and print its score
+  --replay FILE     watch a recorded game
+  --edit FILE       edit the level in FILE, a new one if it doesn't exist yet
+  --config FILE     read settings from FILE instead of the default config
+  --help            show this message";
+
+/// Smallest board side, border included, the snake and the first apple fit on.
+pub const MIN_SIZE: i32 = 10;
+const MAX_SIZE: i32 = 1000;
+const MIN_CELL_SIZE: f64 = 4.0;
+const MAX_CELL_SIZE: f64 = 100.0;
+const MIN_SPEED: f64 = 1.0;
+const MAX_SPEED: f64 = 30.0;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Options {
+    /// Board size when set from the command line or config, `None` to play
+    /// the level files at their own size.
+    pub width: Option<i32>,
+    pub height: Option<i32>,
+    /// Pixels per board cell.
+    pub cell_size: f64,
+    /// Steps per second when a game starts.
+    pub speed: f64,
+    /// Blocks the snakes start with, `None` to keep the levels' own.
+    pub length: Option<usize>,
+    pub wrap: bool,
+    pub theme: String,
+    /// Draws the snakes moving smoothly between steps.
+    pub smooth: bool,
+    /// Backspace takes back steps, the game isn't recorded or scored.
+    pub practice: bool,
+    pub versus: bool,
+    pub cpu: bool,
+    /// Computer snakes to play against in the arena, `None` outside of it.
+    pub arena: Option<usize>,
+    /// Plays today's challenge.
+    pub daily: bool,
+    /// Daily challenge replay to check instead of playing.
+    pub verify: Option<PathBuf>,
+    pub replay: Option<PathBuf>,
+    /// Level file to open in the editor instead of playing.
+    pub edit: Option<PathBuf>,
+    /// Config file named on the command line.
+    pub config: Option<PathBuf>,
+    pub help: bool,
+}
+
+impl Default for Options {
+    fn default() -> Options {
+        Options {
+            width: None,
+            height: None,
+            cell_size: 25.0,
+            speed: 5.0,
+            length: None,
+            wrap: false,
+            theme: String::from("classic"),
+            smooth: false,
+            practice: false,
+            versus: false,
+            cpu: false,
+            arena: None,
+            daily: false,
+            verify: None,
+            replay: None,
+            edit: None,
+            config: None,
+            help: false,
+        }
+    }
+}
+
+impl Options {
+    /// Board size to play on when there are no levels.
+    pub fn board_size(&self) -> (i32, i32) {
+        let (width, height) = if self.arena.is_some() { (48, 36) } else { (40, 30) };
+        (self.width.unwrap_or(width), self.height.unwrap_or(height))
+    }
+
+    /// Whether the level files are played, rather than the open board.
+    pub fn uses_levels(&self) -> bool {
+        self.width.is_none() && self.height.is_none() && self.arena.is_none()
+    }
+
+    /// Reads `args`, the program name left out. `Options::config` tells
+    /// whether a config file was named, see `read_config`.
+    pub fn parse_args<I: IntoIterator<Item = String>>(args: I) -> Result<Options, String> {
+        Options::default().apply_args(args)
+    }
+
+    /// Applies the flags in `args` on top of these options.
+    pub fn apply_args<I: IntoIterator<Item = String>>(mut self, args: I) -> Result<Options, String> {
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let mut value = |flag: &str| {
+                args.next()
+                    .ok_or_else(|| format!("{} needs a value", flag))
+            };
+            match arg.as_str() {
+                "--width" => self.width = Some(parse_value(&value(&arg)?, &arg)?),
+                "--height" => self.height = Some(parse_value(&value(&arg)?, &arg)?),
+                "--cell-size" => self.cell_size = parse_value(&value(&arg)?, &arg)?,
+                "--speed" => self.speed = parse_value(&value(&arg)?, &arg)?,
+                "--length" => self.length = Some(parse_value(&value(&arg)?, &arg)?),
+                "--arena" => self.arena = Some(parse_value(&value(&arg)?, &arg)?),
+                "--theme" => self.theme = value(&arg)?,
+                "--replay" => self.replay = Some(PathBuf::from(value(&arg)?)),
+                "--verify" => self.verify = Some(PathBuf::from(value(&arg)?)),
+                "--edit" => self.edit = Some(PathBuf::from(value(&arg)?)),
+                "--config" => self.config = Some(PathBuf::from(value(&arg)?)),
+                "--wrap" => self.wrap = true,
+                "--smooth" => self.smooth = true,
+                "--practice" => self.practice = true,
+                "--versus" => self.versus = true,
+                "--cpu" => self.cpu = true,
+                "--daily" => self.daily = true,
+                "--help" | "-h" => self.help = true,
+                _ => return Err(format!("unknown option '{}'", arg)),
+            }
+        }
+        Ok(self)
+    }
+
+    /// Applies the settings of a config file on top of these options.
+    pub fn apply_config(mut self, text: &str) -> Result<Options, String> {
+        for (number, line) in text.lines().enumerate() {
+            let number = number + 1;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with(';') {
+                continue;
+            }
+
+            let colon = line.find(':')
+                .ok_or_else(|| format!("line {}: expected 'key: value'", number))?;
+            let key = line[..colon].trim();
+            let value = line[colon + 1..].trim();
+            let what = format!("line {}: {}", number, key);
+            match key {
+                "width" => self.width = Some(parse_value(value, &what)?),
+                "height" => self.height = Some(parse_value(value, &what)?),
+                "cell_size" => self.cell_size = parse_value(value, &what)?,
+                "speed" => self.speed = parse_value(value, &what)?,
+                "length" => self.length = Some(parse_value(value, &what)?),
+                "arena" => self.arena = Some(parse_value(value, &what)?),
+                "wrap" => self.wrap = parse_value(value, &what)?,
+                "theme" => self.theme = value.to_string(),
+                "smooth" => self.smooth = parse_value(value, &what)?,
+                "practice" => self.practice = parse_value(value, &what)?,
+                _ => return Err(format!("line {}: unknown setting '{}'", number, key)),
+            }
+        }
+        Ok(self)
+    }
+
+    /// Settings from the config file at `path` on top of these options.
+    pub fn read_config(self, path: &Path) -> Result<Options, String> {
+        let mut text = String::new();
+        File::open(path)
+            .and_then(|mut file| file.read_to_string(&mut text))
+            .map_err(|e| format!("{}: {}", path.display(), e))?;
+        self.apply_config(&text)
+            .map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    /// Checks every value can be played with.
+    pub fn validate(&self) -> Result<(), String> {
+        for &(name, size) in &[("width", self.width), ("height", self.height)] {
+            if let Some(size) = size {
+                if !(MIN_SIZE..=MAX_SIZE).contains(&size) {
+                    return Err(format!(
+                        "the board {} must be between {} and {}, not {}",
+                        name, MIN_SIZE, MAX_SIZE, size
+                    ));
+                }
+            }
+        }
+        if !(MIN_CELL_SIZE..=MAX_CELL_SIZE).contains(&self.cell_size) {
+            return Err(format!(
+                "the cell size must be between {} and {} pixels, not {}",
+                MIN_CELL_SIZE, MAX_CELL_SIZE, self.cell_size
+            ));
+        }
+        if !(MIN_SPEED..=MAX_SPEED).contains(&self.speed) {
+            return Err(format!(
+                "the speed must be between {} and {} steps per second, not {}",
+                MIN_SPEED, MAX_SPEED, self.speed
+            ));
+        }
+        if let Some(length) = self.length {
+            let (width, _) = self.board_size();
+            // the snake starts lying flat along the top of the board
+            if length == 0 || length as i32 > width - 2 {
+                return Err(format!(
+                    "the snake length must be between 1 and {} on a board {} wide, not {}",
+                    width - 2,
+                    width,
+                    length
+                ));
+            }
+        }
+        if self.cpu && self.replay.is_some() {
+            return Err(String::from("--cpu can't be used when watching a replay"));
+        }
+        if self.edit.is_some() && (self.replay.is_some() || self.arena.is_some()) {
+            return Err(String::from("--edit can't be used with --replay or --arena"));
+        }
+        if let Some(snakes) = self.arena {
+            if snakes == 0 || snakes >= MAX_PLAYERS {
+                return Err(format!(
+                    "the arena takes between 1 and {} computer snakes, not {}",
+                    MAX_PLAYERS - 1,
+                    snakes
+                ));
+            }
+            if self.versus || self.cpu || self.replay.is_some() {
+                return Err(String::from("--arena can't be used with --versus, --cpu or --replay"));
+            }
+        }
+        if self.daily
+            && (self.practice || self.versus || self.cpu || self.arena.is_some() || self.replay.is_some()
+                || self.edit.is_some())
+        {
+            return Err(String::from(
+                "--daily can't be used with --practice, --versus, --cpu, --arena, --replay or --edit",
+            ));
+        }
+        Ok(())
+    }
+}
+
+fn parse_value<T: ::std::str::FromStr>(value: &str, what: &str) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("{}: '{}' is not a valid value", what, value))
+}
@@ -0,0 +1,325 @@
+//! Recorded games.
+//!
+//! A `Game` only depends on its seed, its setup and the turns made on each
+//! step, so that is all a replay keeps. The file is a small header followed
+//! by one entry per step:
+//!
+//! ```text
+//! "SNKR" version:u8 seed:u64 width:u16 height:u16 wrap:u8 arena:u8
+//! players:u8 rounds_to_win:u32 time_limit:u32 level_count:u16
+//! (length:u32 level_text)* steps...
+//! ```
+//!
+//! Numbers are little endian, a time limit of 0 means there is none. A
+//! step is one byte per player, 0 for no turn and 1 to 4 for up, down, left
+//! and right. A byte of `0x80 + n` stands for `n` steps without any turn (up
+//! to 126) and `0xFF` for a restart.
+
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::path::Path;
+use std::sync::Arc;
+
+use game::{rng_from_seed, Event, Game, GameConfig, MAX_PLAYERS};
+use level::Level;
+use snake::Direction;
+
+const MAGIC: &[u8; 4] = b"SNKR";
+/// Bumped whenever the rules change, old replays would play out differently.
+const VERSION: u8 = 4;
+
+const IDLE: u8 = 0x80;
+const MAX_IDLE: u8 = 0x7E;
+const RESTART: u8 = 0xFF;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Move {
+    /// One `Game::step_all`, a turn or `None` per player.
+    Step(Vec<Option<Direction>>),
+    /// `Game::restart` after a round or game ended.
+    Restart,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Replay {
+    pub seed: u64,
+    pub config: GameConfig,
+    /// Levels played, empty for the classic board.
+    pub levels: Vec<Level>,
+    pub moves: Vec<Move>,
+}
+
+impl Replay {
+    /// An empty recording of a game started with these settings.
+    pub fn new(config: GameConfig, levels: Vec<Level>, seed: u64) -> Replay {
+        Replay {
+            seed,
+            config,
+            levels,
+            moves: Vec::new(),
+        }
+    }
+
+    /// The game as it was when the recording started.
+    pub fn game(&self) -> Game {
+        if self.levels.is_empty() {
+            Game::with_config(self.config, self.seed)
+        } else {
+            let levels = Arc::new(self.levels.clone());
+            Game::with_levels(self.config, levels, rng_from_seed(self.seed))
+        }
+    }
+
+    pub fn record_step(&mut self, dirs: &[Option<Direction>]) {
+        self.moves.push(Move::Step(dirs.to_vec()));
+    }
+
+    pub fn record_restart(&mut self) {
+        self.moves.push(Move::Restart);
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = MAGIC.to_vec();
+        bytes.push(VERSION);
+        bytes.extend_from_slice(&self.seed.to_le_bytes());
+        bytes.extend_from_slice(&(self.config.width as u16).to_le_bytes());
+        bytes.extend_from_slice(&(self.config.height as u16).to_le_bytes());
+        bytes.push(self.config.wrap as u8);
+        bytes.push(self.config.arena as u8);
+        bytes.push(self.config.players as u8);
+        bytes.extend_from_slice(&self.config.rounds_to_win.to_le_bytes());
+        bytes.extend_from_slice(&self.config.time_limit.unwrap_or(0).to_le_bytes());
+
+        bytes.extend_from_slice(&(self.levels.len() as u16).to_le_bytes());
+        for level in &self.levels {
+            let text = level.to_text();
+            bytes.extend_from_slice(&(text.len() as u32).to_le_bytes());
+            bytes.extend_from_slice(text.as_bytes());
+        }
+
+        let mut idle = 0;
+        for m in &self.moves {
+            match *m {
+                Move::Step(ref dirs) if dirs.iter().all(|d| d.is_none()) => {
+                    idle += 1;
+                    if idle == MAX_IDLE {
+                        bytes.push(IDLE + idle);
+                        idle = 0;
+                    }
+                    continue;
+                }
+                _ => {}
+            }
+            if idle > 0 {
+                bytes.push(IDLE + idle);
+                idle = 0;
+            }
+            match *m {
+                Move::Step(ref dirs) => {
+                    for player in 0..self.config.players {
+                        bytes.push(dirs.get(player).cloned().unwrap_or(None).map_or(0, direction_code));
+                    }
+                }
+                Move::Restart => bytes.push(RESTART),
+            }
+        }
+        if idle > 0 {
+            bytes.push(IDLE + idle);
+        }
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Replay, String> {
+        let mut reader = Reader { bytes, pos: 0 };
+        if reader.take(4)? != MAGIC {
+            return Err(String::from("not a replay file"));
+        }
+        let version = reader.u8()?;
+        if version != VERSION {
+            return Err(format!("unsupported replay version {}", version));
+        }
+
+        let seed = reader.u64()?;
+        let width = reader.u16()? as i32;
+        let height = reader.u16()? as i32;
+        let wrap = reader.u8()? != 0;
+        let arena = reader.u8()? != 0;
+        let players = reader.u8()? as usize;
+        let rounds_to_win = reader.u32()?;
+        let time_limit = match reader.u32()? {
+            0 => None,
+            limit => Some(limit),
+        };
+        if players == 0 {
+            return Err(String::from("the replay has no players"));
+        }
+        if players > MAX_PLAYERS {
+            return Err(format!("the replay has {} players, at most {} can play", players, MAX_PLAYERS));
+        }
+        let config = GameConfig {
+            width,
+            height,
+            wrap,
+            players,
+            rounds_to_win,
+            arena,
+            time_limit,
+        };
+
+        let mut levels = Vec::new();
+        for _ in 0..reader.u16()? {
+            let length = reader.u32()? as usize;
+            let text = String::from_utf8(reader.take(length)?.to_vec())
+                .map_err(|_| String::from("level text is not utf-8"))?;
+            levels.push(Level::parse(&text)?);
+        }
+
+        let mut moves = Vec::new();
+        while !reader.is_done() {
+            let byte = reader.u8()?;
+            if byte == RESTART {
+                moves.push(Move::Restart);
+            } else if byte >= IDLE {
+                for _ in 0..byte - IDLE {
+                    moves.push(Move::Step(vec![None; players]));
+                }
+            } else {
+                let mut dirs = vec![code_direction(byte)?];
+                for _ in 1..players {
+                    dirs.push(code_direction(reader.u8()?)?);
+                }
+                moves.push(Move::Step(dirs));
+            }
+        }
+
+        Ok(Replay {
+            seed,
+            config,
+            levels,
+            moves,
+        })
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        File::create(path)?.write_all(&self.to_bytes())
+    }
+
+    pub fn load(path: &Path) -> Result<Replay, String> {
+        let mut bytes = Vec::new();
+        File::open(path)
+            .and_then(|mut file| file.read_to_end(&mut bytes))
+            .map_err(|e| format!("{}: {}", path.display(), e))?;
+        Replay::from_bytes(&bytes).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+}
+
+/// Plays the moves of a replay back onto a game.
+#[derive(Debug, Clone)]
+pub struct Playback {
+    replay: Replay,
+    position: usize,
+}
+
+impl Playback {
+    pub fn new(replay: Replay) -> Playback {
+        Playback {
+            replay,
+            position: 0,
+        }
+    }
+
+    pub fn replay(&self) -> &Replay {
+        &self.replay
+    }
+
+    /// Moves played so far.
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.position >= self.replay.moves.len()
+    }
+
+    /// Whether the next move restarts the game.
+    pub fn restarts_next(&self) -> bool {
+        self.replay.moves.get(self.position) == Some(&Move::Restart)
+    }
+
+    /// Plays the next move on `game`, which must have been built with
+    /// `Replay::game` and only been driven by this playback. `None` once
+    /// every move was played.
+    pub fn step(&mut self, game: &mut Game) -> Option<Vec<Event>> {
+        let events = match *self.replay.moves.get(self.position)? {
+            Move::Step(ref dirs) => game.step_all(dirs),
+            Move::Restart => {
+                game.restart();
+                Vec::new()
+            }
+        };
+        self.position += 1;
+        Some(events)
+    }
+}
+
+fn direction_code(dir: Direction) -> u8 {
+    match dir {
+        Direction::Up => 1,
+        Direction::Down => 2,
+        Direction::Left => 3,
+        Direction::Right => 4,
+    }
+}
+
+fn code_direction(code: u8) -> Result<Option<Direction>, String> {
+    match code {
+        0 => Ok(None),
+        1 => Ok(Some(Direction::Up)),
+        2 => Ok(Some(Direction::Down)),
+        3 => Ok(Some(Direction::Left)),
+        4 => Ok(Some(Direction::Right)),
+        _ => Err(format!("bad turn {} in the replay", code)),
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn is_done(&self) -> bool {
+        self.pos >= self.bytes.len()
+    }
+
+    fn take(&mut self, count: usize) -> Result<&'a [u8], String> {
+        if self.bytes.len() - self.pos < count {
+            return Err(String::from("the replay ends too early"));
+        }
+        let taken = &self.bytes[self.pos..self.pos + count];
+        self.pos += count;
+        Ok(taken)
+    }
+
+    fn u8(&mut self) -> Result<u8, String> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, String> {
+        let mut b = [0; 2];
+        b.copy_from_slice(self.take(2)?);
+        Ok(u16::from_le_bytes(b))
+    }
+
+    fn u32(&mut self) -> Result<u32, String> {
+        let mut b = [0; 4];
+        b.copy_from_slice(self.take(4)?);
+        Ok(u32::from_le_bytes(b))
+    }
+
+    fn u64(&mut self) -> Result<u64, String> {
+        let mut b = [0; 8];
+        b.copy_from_slice(self.take(8)?);
+        Ok(u64::from_le_bytes(b))
+    }
+}
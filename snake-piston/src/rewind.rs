@@ -0,0 +1,65 @@
+//! Taking back steps, for practice.
+//!
+//! `Rewind` keeps a `Snapshot` of the game from before each step, up to a
+//! fixed number of them, forgetting the oldest first. Games that don't
+//! practice simply have no `Rewind` and pay nothing for it.
+
+use std::collections::VecDeque;
+
+use game::{Game, Snapshot};
+
+pub struct Rewind {
+    snapshots: VecDeque<Snapshot>,
+    capacity: usize,
+}
+
+impl Rewind {
+    /// Remembers up to `capacity` steps.
+    pub fn new(capacity: usize) -> Rewind {
+        Rewind {
+            snapshots: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Steps that can be taken back.
+    pub fn len(&self) -> usize {
+        self.snapshots.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.snapshots.is_empty()
+    }
+
+    /// Remembers `game` as it is, before the step about to be played.
+    pub fn record(&mut self, game: &Game) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.snapshots.len() == self.capacity {
+            self.snapshots.pop_front();
+        }
+        self.snapshots.push_back(game.snapshot());
+    }
+
+    /// Takes `game` back one step. Returns false when there is nothing left
+    /// to take back.
+    pub fn step_back(&mut self, game: &mut Game) -> bool {
+        match self.snapshots.pop_back() {
+            Some(snapshot) => {
+                game.restore(snapshot);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Forgets every step, for when a new game starts.
+    pub fn clear(&mut self) {
+        self.snapshots.clear();
+    }
+}
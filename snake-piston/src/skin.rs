@@ -0,0 +1,86 @@
+//! Themes as the window draws them, their sprites loaded into textures.
+
+use piston_window::*;
+
+use rusty_snake::theme::{Sprites, Theme};
+
+/// Textures of a theme's sprites, `None` where it has none or the image
+/// couldn't be loaded.
+#[derive(Clone)]
+pub struct Skin {
+    pub head: Option<G2dTexture>,
+    pub body: Option<G2dTexture>,
+    pub tail: Option<G2dTexture>,
+    pub food: Option<G2dTexture>,
+}
+
+impl Skin {
+    fn none() -> Skin {
+        Skin {
+            head: None,
+            body: None,
+            tail: None,
+            food: None,
+        }
+    }
+
+    fn load(sprites: &Sprites, factory: &mut GfxFactory) -> Skin {
+        // sprites are small pixel art, keep their pixels sharp
+        let settings = TextureSettings::new().filter(Filter::Nearest);
+        let mut texture = |sprite: &Option<_>| {
+            let path = sprite.as_ref()?;
+            Texture::from_path(&mut *factory, path, Flip::None, &settings)
+                .map_err(|e| println!("Could not load the sprite, drawing blocks instead. {}", e))
+                .ok()
+        };
+        Skin {
+            head: texture(&sprites.head),
+            body: texture(&sprites.body),
+            tail: texture(&sprites.tail),
+            food: texture(&sprites.food),
+        }
+    }
+}
+
+/// Every theme there is, switched between while playing.
+#[derive(Clone)]
+pub struct Themes {
+    themes: Vec<Theme>,
+    skins: Vec<Skin>,
+    current: usize,
+}
+
+impl Themes {
+    /// Only the classic colors, until the window can load the real ones.
+    pub fn classic() -> Themes {
+        Themes {
+            themes: vec![Theme::classic()],
+            skins: vec![Skin::none()],
+            current: 0,
+        }
+    }
+
+    /// `themes` with their sprites, starting on the one called `name`.
+    pub fn load(themes: Vec<Theme>, name: &str, factory: &mut GfxFactory) -> Themes {
+        let skins = themes.iter().map(|theme| Skin::load(&theme.sprites, factory)).collect();
+        let current = themes.iter().position(|theme| theme.name == name).unwrap_or(0);
+        Themes {
+            themes,
+            skins,
+            current,
+        }
+    }
+
+    pub fn theme(&self) -> &Theme {
+        &self.themes[self.current]
+    }
+
+    pub fn skin(&self) -> &Skin {
+        &self.skins[self.current]
+    }
+
+    /// Moves on to the next theme, back to the first after the last.
+    pub fn next(&mut self) {
+        self.current = (self.current + 1) % self.themes.len();
+    }
+}
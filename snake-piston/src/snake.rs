@@ -1,12 +1,6 @@
-use std::collections::LinkedList;
-use piston_window::{Context, G2d};
-use piston_window::types::Color;
+use std::collections::VecDeque;
 
-use draw::draw_block;
-
-const SNAKE_COLOR: Color = [0.00, 0.80, 0.00, 1.0];
-
-#[derive(Copy, Clone, PartialEq)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum Direction {
     Up,
     Down,
@@ -15,6 +9,10 @@ pub enum Direction {
 }
 
 impl Direction {
+    pub fn all() -> [Direction; 4] {
+        [Direction::Up, Direction::Down, Direction::Left, Direction::Right]
+    }
+
     pub fn opposite(&self) -> Direction {
         match *self {
             Direction::Up => Direction::Down,
@@ -23,23 +21,63 @@ impl Direction {
             Direction::Right => Direction::Left,
         }
     }
+
+    /// The direction after a quarter turn to the left.
+    pub fn turn_left(&self) -> Direction {
+        match *self {
+            Direction::Up => Direction::Left,
+            Direction::Left => Direction::Down,
+            Direction::Down => Direction::Right,
+            Direction::Right => Direction::Up,
+        }
+    }
+
+    pub fn turn_right(&self) -> Direction {
+        self.turn_left().opposite()
+    }
 }
 
-#[derive(Debug, Clone)]
-struct Block {
-    x: i32,
-    y: i32,
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Block {
+    pub x: i32,
+    pub y: i32,
 }
 
+/// Inclusive rectangle of cells the snake can move in.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Bounds {
+    pub left: i32,
+    pub top: i32,
+    pub right: i32,
+    pub bottom: i32,
+}
+
+impl Bounds {
+    pub fn contains(&self, x: i32, y: i32) -> bool {
+        x >= self.left && x <= self.right && y >= self.top && y <= self.bottom
+    }
+
+    /// Brings a cell that stepped off one edge back in on the opposite one.
+    pub fn wrap(&self, x: i32, y: i32) -> (i32, i32) {
+        let width = self.right - self.left + 1;
+        let height = self.bottom - self.top + 1;
+        (
+            self.left + (x - self.left).rem_euclid(width),
+            self.top + (y - self.top).rem_euclid(height),
+        )
+    }
+}
+
+#[derive(Clone)]
 pub struct Snake {
     direction: Direction,
-    body: LinkedList<Block>,
+    body: VecDeque<Block>,
     tail: Option<Block>,
 }
 
 impl Snake {
     pub fn new(x: i32, y: i32) -> Snake {
-        let mut body: LinkedList<Block> = LinkedList::new();
+        let mut body: VecDeque<Block> = VecDeque::with_capacity(3);
         body.push_back(Block {
             x: x + 2,
             y,
@@ -60,86 +98,104 @@ impl Snake {
         }
     }
 
-    pub fn draw(&self, con: &Context, g: &mut G2d) {
-        for block in &self.body {
-            draw_block(SNAKE_COLOR, block.x, block.y, con, g);
+    /// Snake lying on `cells`, head first, heading towards `direction`.
+    pub fn spawn(cells: &[(i32, i32)], direction: Direction) -> Snake {
+        Snake {
+            direction,
+            body: cells.iter().map(|&(x, y)| Block { x, y }).collect(),
+            tail: None,
         }
     }
 
+    /// Body blocks from head to tail.
+    pub fn blocks<'a>(&'a self) -> impl Iterator<Item = &'a Block> + 'a {
+        self.body.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.body.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.body.is_empty()
+    }
+
     pub fn head_position(&self) -> (i32, i32) {
         let head_block = self.body.front().unwrap();
         (head_block.x, head_block.y)
     }
 
-    pub fn move_forward(&mut self, dir: Option<Direction>) {
-        match dir {
-            Some(d) => self.direction = d,
-            None => (),
+    pub fn tail_position(&self) -> (i32, i32) {
+        let tail_block = self.body.back().unwrap();
+        (tail_block.x, tail_block.y)
+    }
+
+    /// Moves one cell, wrapping around `wrap` when given. Returns the tail
+    /// block left behind.
+    pub fn move_forward(&mut self, dir: Option<Direction>, wrap: Option<Bounds>) -> Block {
+        if let Some(d) = dir {
+            self.direction = d;
         }
 
-        let (last_x, last_y): (i32, i32) = self.head_position();
-
-        let new_block = match self.direction {
-            Direction::Up => Block {
-                x: last_x,
-                y: last_y - 1,
-            },
-            Direction::Down => Block {
-                x: last_x,
-                y: last_y + 1,
-            },
-            Direction::Left => Block {
-                x: last_x - 1,
-                y: last_y,
-            },
-            Direction::Right => Block {
-                x: last_x + 1,
-                y: last_y,
-            },
-        };
-        self.body.push_front(new_block);
+        let (x, y) = self.next_head(None, wrap);
+        self.body.push_front(Block { x, y });
         let removed_block = self.body.pop_back().unwrap();
         self.tail = Some(removed_block);
+        removed_block
+    }
+
+    /// Whether the snake moved since it spawned.
+    pub fn has_moved(&self) -> bool {
+        self.tail.is_some()
+    }
+
+    /// The cell the tail moved off on the last step, `None` when the snake
+    /// grew instead or hasn't moved yet.
+    pub fn left_tail(&self) -> Option<(i32, i32)> {
+        match self.tail {
+            Some(block) if self.body.back() != Some(&block) => Some((block.x, block.y)),
+            _ => None,
+        }
     }
 
     pub fn head_direction(&self) -> Direction {
         self.direction
     }
 
-    pub fn next_head(&self, dir: Option<Direction>) -> (i32, i32) {
+    pub fn next_head(&self, dir: Option<Direction>, wrap: Option<Bounds>) -> (i32, i32) {
         let (head_x, head_y): (i32, i32) = self.head_position();
 
-        let mut moving_dir = self.direction;
-        match dir {
-            Some(d) => moving_dir = d,
-            None => {}
-        }
+        let moving_dir = dir.unwrap_or(self.direction);
 
-        match moving_dir {
+        let next = match moving_dir {
             Direction::Up => (head_x, head_y - 1),
             Direction::Down => (head_x, head_y + 1),
             Direction::Left => (head_x - 1, head_y),
             Direction::Right => (head_x + 1, head_y),
+        };
+
+        match wrap {
+            Some(bounds) => bounds.wrap(next.0, next.1),
+            None => next,
         }
     }
 
-    pub fn restore_tail(&mut self) {
-        let blk = self.tail.clone().unwrap();
+    /// Puts back the tail block left behind by the last move, returning it.
+    pub fn restore_tail(&mut self) -> Block {
+        let blk = self.tail.unwrap();
         self.body.push_back(blk);
+        blk
     }
 
-    pub fn overlap_tail(&self, x: i32, y: i32) -> bool {
-        let mut ch = 0;
-        for block in &self.body {
-            if x == block.x && y == block.y {
-                return true;
-            }
+    /// Drops up to `blocks` blocks off the tail, keeping at least two.
+    /// Returns the blocks dropped.
+    pub fn shrink(&mut self, blocks: usize) -> Vec<Block> {
+        let keep = self.body.len().saturating_sub(blocks).max(2).min(self.body.len());
+        self.body.split_off(keep).into_iter().collect()
+    }
 
-            ch += 1;
-            if ch == self.body.len() - 1 {
-                break;
-            }
-        }
-        return false;
+    /// Whether any block of the body, tail included, is on `(x, y)`.
+    pub fn overlap(&self, x: i32, y: i32) -> bool {
+        self.body.iter().any(|block| block.x == x && block.y == y)
     }
-}
\ No newline at end of file
+}
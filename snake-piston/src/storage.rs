@@ -0,0 +1,86 @@
+//! Files kept between runs, in the user's data directory.
+
+use std::env;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Write};
+use std::path::PathBuf;
+
+use daily::DailyResult;
+
+const BEST_SCORE_FILE: &str = "best_score.txt";
+const REPLAYS_DIR: &str = "replays";
+const CONFIG_FILE: &str = "config.txt";
+const DAILY_FILE: &str = "daily.txt";
+
+/// `rusty-snake` inside the platform's data directory: `$XDG_DATA_HOME`,
+/// `~/.local/share` or `%APPDATA%`.
+pub fn data_dir() -> Option<PathBuf> {
+    let base = env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("APPDATA").map(PathBuf::from))
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/share")))?;
+    Some(base.join("rusty-snake"))
+}
+
+/// `rusty-snake/config.txt` inside the platform's config directory:
+/// `$XDG_CONFIG_HOME`, `~/.config` or `%APPDATA%`.
+pub fn config_file() -> Option<PathBuf> {
+    let base = env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("APPDATA").map(PathBuf::from))
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+    Some(base.join("rusty-snake").join(CONFIG_FILE))
+}
+
+/// Best single player score so far, 0 when there is none yet.
+pub fn load_best_score() -> u32 {
+    let path = match data_dir() {
+        Some(dir) => dir.join(BEST_SCORE_FILE),
+        None => return 0,
+    };
+    let mut text = String::new();
+    match File::open(path).and_then(|mut file| file.read_to_string(&mut text)) {
+        Ok(_) => text.trim().parse().unwrap_or(0),
+        Err(_) => 0,
+    }
+}
+
+pub fn save_best_score(score: u32) -> io::Result<()> {
+    let dir = data_dir().ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no data directory"))?;
+    fs::create_dir_all(&dir)?;
+    let mut file = File::create(dir.join(BEST_SCORE_FILE))?;
+    writeln!(file, "{}", score)
+}
+
+/// Where finished games are recorded, created when missing.
+pub fn replays_dir() -> io::Result<PathBuf> {
+    let dir = data_dir()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no data directory"))?
+        .join(REPLAYS_DIR);
+    fs::create_dir_all(&dir)?;
+    Ok(dir)
+}
+
+/// Daily challenge runs so far, oldest first.
+pub fn load_daily_results() -> Vec<DailyResult> {
+    let path = match data_dir() {
+        Some(dir) => dir.join(DAILY_FILE),
+        None => return Vec::new(),
+    };
+    let mut text = String::new();
+    match File::open(path).and_then(|mut file| file.read_to_string(&mut text)) {
+        Ok(_) => text.lines().filter_map(DailyResult::parse).collect(),
+        Err(_) => Vec::new(),
+    }
+}
+
+/// Adds a run to the end of the daily results file.
+pub fn save_daily_result(result: &DailyResult) -> io::Result<()> {
+    let dir = data_dir().ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no data directory"))?;
+    fs::create_dir_all(&dir)?;
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(dir.join(DAILY_FILE))?;
+    writeln!(file, "{}", result.to_line())
+}
@@ -0,0 +1,228 @@
+//! Color themes and sprites.
+//!
+//! A theme file has one `key: value` line per color, colors written as
+//! `#rrggbb` or `#rrggbbaa`. Anything left out keeps its classic color:
+//!
+//! ```text
+//! ; lines starting with ';' are comments
+//! name: nokia
+//! background: #c7f0d8
+//! border: #43523d
+//! players: #43523d #5a6e52
+//! head: sprites/nokia-head.png
+//! ```
+//!
+//! `players` lists the snake colors in player order, repeated when there
+//! are fewer than eight. `head`, `body`, `tail` and `food` name images,
+//! relative to the theme file, drawn over a cell instead of filling it.
+//! They are drawn tinted with the snake or food color, for a snake heading
+//! right, and turned to the way each block is going.
+
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use food::{Effect, FoodKind};
+
+/// Red, green, blue and alpha, from 0 to 1.
+pub type Color = [f32; 4];
+
+/// Images replacing the plain blocks, `None` for a block of color.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Sprites {
+    pub head: Option<PathBuf>,
+    pub body: Option<PathBuf>,
+    pub tail: Option<PathBuf>,
+    pub food: Option<PathBuf>,
+}
+
+/// Every color the game is drawn with.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Theme {
+    pub name: String,
+    pub background: Color,
+    pub border: Color,
+    /// Dashes on the border of a wrapping board.
+    pub portal: Color,
+    /// Tint over the board once the game is over.
+    pub game_over: Color,
+    pub hud: Color,
+    /// Shade behind the title, pause and game over text.
+    pub screen: Color,
+    /// Snake color of each player, in player order.
+    pub players: [Color; 8],
+    pub apple: Color,
+    pub bonus: Color,
+    pub shrink: Color,
+    pub slow: Color,
+    pub fast: Color,
+    pub ghost: Color,
+    /// Food left by a dead snake in the arena.
+    pub remains: Color,
+    pub sprites: Sprites,
+}
+
+impl Theme {
+    /// The colors the game always had, for when no theme file can be read.
+    pub fn classic() -> Theme {
+        Theme {
+            name: String::from("classic"),
+            background: [0.50, 0.50, 0.50, 1.0],
+            border: [0.00, 0.00, 0.00, 1.0],
+            portal: [0.30, 0.30, 0.90, 1.0],
+            game_over: [0.90, 0.00, 0.00, 0.4],
+            hud: [1.00, 1.00, 1.00, 1.0],
+            screen: [0.00, 0.00, 0.00, 0.6],
+            players: [
+                [0.00, 0.80, 0.00, 1.0],
+                [0.95, 0.60, 0.00, 1.0],
+                [0.10, 0.45, 0.75, 1.0],
+                [0.85, 0.30, 0.65, 1.0],
+                [0.00, 0.65, 0.65, 1.0],
+                [0.55, 0.35, 0.15, 1.0],
+                [0.15, 0.20, 0.55, 1.0],
+                [0.70, 0.70, 0.25, 1.0],
+            ],
+            apple: [0.80, 0.00, 0.00, 1.0],
+            bonus: [1.00, 0.85, 0.00, 1.0],
+            shrink: [0.60, 0.20, 0.80, 1.0],
+            slow: [0.20, 0.60, 1.00, 1.0],
+            fast: [1.00, 0.45, 0.10, 1.0],
+            ghost: [0.90, 0.90, 0.90, 1.0],
+            remains: [0.85, 0.55, 0.50, 1.0],
+            sprites: Sprites::default(),
+        }
+    }
+
+    /// Reads a theme file. Sprite paths are left as written, see `load`.
+    pub fn parse(text: &str) -> Result<Theme, String> {
+        let mut theme = Theme::classic();
+        theme.name = String::from("Unnamed");
+
+        for (number, line) in text.lines().enumerate() {
+            let number = number + 1;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with(';') {
+                continue;
+            }
+
+            let colon = line.find(':')
+                .ok_or_else(|| format!("line {}: expected 'key: value'", number))?;
+            let key = line[..colon].trim();
+            let value = line[colon + 1..].trim();
+            let not_a_color = |text: &str| format!("line {}: '{}' is not a color", number, text);
+            let color = || parse_color(value).ok_or_else(|| not_a_color(value));
+            match key {
+                "name" => theme.name = value.to_string(),
+                "background" => theme.background = color()?,
+                "border" => theme.border = color()?,
+                "portal" => theme.portal = color()?,
+                "game_over" => theme.game_over = color()?,
+                "hud" => theme.hud = color()?,
+                "screen" => theme.screen = color()?,
+                "apple" => theme.apple = color()?,
+                "bonus" => theme.bonus = color()?,
+                "shrink" => theme.shrink = color()?,
+                "slow" => theme.slow = color()?,
+                "fast" => theme.fast = color()?,
+                "ghost" => theme.ghost = color()?,
+                "remains" => theme.remains = color()?,
+                "players" => {
+                    let colors = value
+                        .split_whitespace()
+                        .map(|color| parse_color(color).ok_or_else(|| not_a_color(color)))
+                        .collect::<Result<Vec<Color>, String>>()?;
+                    if colors.is_empty() || colors.len() > theme.players.len() {
+                        return Err(format!(
+                            "line {}: players takes between 1 and {} colors",
+                            number,
+                            theme.players.len()
+                        ));
+                    }
+                    for (i, player) in theme.players.iter_mut().enumerate() {
+                        *player = colors[i % colors.len()];
+                    }
+                }
+                "head" => theme.sprites.head = Some(PathBuf::from(value)),
+                "body" => theme.sprites.body = Some(PathBuf::from(value)),
+                "tail" => theme.sprites.tail = Some(PathBuf::from(value)),
+                "food" => theme.sprites.food = Some(PathBuf::from(value)),
+                _ => return Err(format!("line {}: unknown setting '{}'", number, key)),
+            }
+        }
+        Ok(theme)
+    }
+
+    /// Reads the theme file at `path`, its sprites found next to it.
+    pub fn load(path: &Path) -> Result<Theme, String> {
+        let mut text = String::new();
+        File::open(path)
+            .and_then(|mut file| file.read_to_string(&mut text))
+            .map_err(|e| format!("{}: {}", path.display(), e))?;
+        let mut theme = Theme::parse(&text).map_err(|e| format!("{}: {}", path.display(), e))?;
+
+        let dir = path.parent().unwrap_or_else(|| Path::new(""));
+        let sprites = &mut theme.sprites;
+        for sprite in [&mut sprites.head, &mut sprites.body, &mut sprites.tail, &mut sprites.food] {
+            *sprite = sprite.take().map(|file| dir.join(file));
+        }
+        Ok(theme)
+    }
+
+    pub fn food(&self, kind: FoodKind) -> Color {
+        match kind {
+            FoodKind::Apple => self.apple,
+            FoodKind::Bonus => self.bonus,
+            FoodKind::Shrink => self.shrink,
+            FoodKind::Slow => self.slow,
+            FoodKind::Fast => self.fast,
+            FoodKind::Ghost => self.ghost,
+            FoodKind::Remains => self.remains,
+        }
+    }
+
+    pub fn effect(&self, effect: Effect) -> Color {
+        match effect {
+            Effect::Slow => self.slow,
+            Effect::Fast => self.fast,
+            Effect::Ghost => self.ghost,
+        }
+    }
+}
+
+/// Every `.txt` theme in `dir`, in file name order. Names must be unique.
+pub fn load_themes(dir: &Path) -> Result<Vec<Theme>, String> {
+    let mut paths = Vec::new();
+    let entries = fs::read_dir(dir).map_err(|e| format!("{}: {}", dir.display(), e))?;
+    for entry in entries {
+        let path = entry.map_err(|e| format!("{}: {}", dir.display(), e))?.path();
+        if path.extension().and_then(|ext| ext.to_str()) == Some("txt") {
+            paths.push(path);
+        }
+    }
+    paths.sort();
+
+    let mut themes: Vec<Theme> = Vec::new();
+    for path in paths {
+        let theme = Theme::load(&path)?;
+        if themes.iter().any(|other| other.name == theme.name) {
+            return Err(format!(
+                "{}: there already is a theme called '{}'",
+                path.display(),
+                theme.name
+            ));
+        }
+        themes.push(theme);
+    }
+    Ok(themes)
+}
+
+/// `#rrggbb` or `#rrggbbaa`.
+pub fn parse_color(text: &str) -> Option<Color> {
+    if !text.starts_with('#') || !text.is_ascii() || (text.len() != 7 && text.len() != 9) {
+        return None;
+    }
+    let channel = |i: usize| u8::from_str_radix(&text[i..i + 2], 16).ok().map(|c| c as f32 / 255.0);
+    let alpha = if text.len() == 9 { channel(7)? } else { 1.0 };
+    Some([channel(1)?, channel(3)?, channel(5)?, alpha])
+}
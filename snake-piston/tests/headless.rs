@@ -0,0 +1,740 @@
+extern crate rand;
+extern crate rusty_snake;
+
+use std::path::Path;
+use std::sync::Arc;
+
+use rand::Rng;
+
+use rusty_snake::ai::{Controller, Greedy, Hamiltonian};
+use rusty_snake::daily::{self, Challenge, DailyResult, Date};
+use rusty_snake::env::{Action, Env, EnvConfig, Observation, VecEnv, FEATURES, GRID_CHANNELS};
+use rusty_snake::food::FoodKind;
+use rusty_snake::game::{rng_from_seed, Event, Game, GameConfig};
+use rusty_snake::input::InputQueue;
+use rusty_snake::level::{self, Cell, Level};
+use rusty_snake::options::Options;
+use rusty_snake::replay::{Move, Playback, Replay};
+use rusty_snake::rewind::Rewind;
+use rusty_snake::snake::Direction;
+use rusty_snake::theme::{self, Theme};
+
+const DIRECTIONS: [Option<Direction>; 5] = [
+    None,
+    Some(Direction::Up),
+    Some(Direction::Down),
+    Some(Direction::Left),
+    Some(Direction::Right),
+];
+
+/// Plays a whole game with random key presses, returning every event.
+fn play_random(seed: u64, max_steps: usize) -> Vec<Event> {
+    let mut game = Game::new(40, 30, seed);
+    let mut keys = rng_from_seed(seed.wrapping_add(1));
+    let mut log = Vec::new();
+
+    for _ in 0..max_steps {
+        if game.is_game_over() {
+            break;
+        }
+        let dir = *keys.choose(&DIRECTIONS).unwrap();
+        log.extend(game.step(dir));
+
+        for block in game.snake(0).blocks() {
+            assert!(block.x > 0 && block.x < game.width() - 1);
+            assert!(block.y > 0 && block.y < game.height() - 1);
+        }
+    }
+    log
+}
+
+#[test]
+fn eats_the_first_food() {
+    let mut game = Game::new(40, 30, 7);
+    assert_eq!(game.snake(0).head_position(), (4, 2));
+    assert_eq!(game.food(), Some((6, 4)));
+
+    game.step(None);
+    game.step(None);
+    game.step(Some(Direction::Down));
+    let events = game.step(None);
+
+    assert_eq!(events[0], Event::Moved { player: 0, x: 6, y: 4 });
+    assert_eq!(events[1], Event::Ate { player: 0, kind: FoodKind::Apple, x: 6, y: 4 });
+    assert_eq!(events[2], Event::Grew { player: 0, length: 4 });
+    assert!(matches!(events[3], Event::FoodSpawned { kind: FoodKind::Apple, .. }));
+    assert_eq!(game.snake(0).len(), 4);
+}
+
+#[test]
+fn dies_on_the_border() {
+    let mut game = Game::new(40, 30, 7);
+    let mut events = Vec::new();
+    for _ in 0..40 {
+        events = game.step(Some(Direction::Up));
+        if game.is_game_over() {
+            break;
+        }
+    }
+    assert_eq!(events, vec![Event::Died { player: 0 }]);
+    assert_eq!(game.snake(0).head_position(), (4, 1));
+    assert!(game.step(None).is_empty());
+}
+
+#[test]
+fn reversing_is_ignored() {
+    let mut game = Game::new(40, 30, 7);
+    game.step(Some(Direction::Left));
+    assert!(!game.is_game_over());
+    assert_eq!(game.snake(0).head_position(), (5, 2));
+    assert_eq!(game.snake(0).head_direction(), Direction::Right);
+}
+
+#[test]
+fn same_seed_same_game() {
+    for seed in 0..50 {
+        assert_eq!(play_random(seed, 2000), play_random(seed, 2000));
+    }
+}
+
+#[test]
+fn thousands_of_scripted_games() {
+    for seed in 0..2000 {
+        let log = play_random(seed, 500);
+        let ate = log.iter()
+            .filter(|e| matches!(**e, Event::Ate { kind: FoodKind::Apple, .. }))
+            .count();
+        let spawned = log.iter()
+            .filter(|e| matches!(**e, Event::FoodSpawned { kind: FoodKind::Apple, .. }))
+            .count();
+        assert_eq!(ate, spawned);
+    }
+}
+
+#[test]
+fn wraps_around_the_edges() {
+    let mut config = GameConfig::new(40, 30);
+    config.wrap = true;
+    let mut game = Game::with_config(config, 7);
+
+    let mut wrapped = false;
+    for _ in 0..40 {
+        let events = game.step(Some(Direction::Up));
+        assert!(!game.is_game_over());
+        if events.contains(&Event::Moved { player: 0, x: 4, y: 28 }) {
+            wrapped = true;
+            break;
+        }
+    }
+    assert!(wrapped);
+}
+
+#[test]
+fn walls_and_level_sequence() {
+    let first = Level::parse("apples: 1\nlength: 1\n@.o.#.\n......\n").unwrap();
+    let second = Level::parse("direction: left\nlength: 2\n.#..@.\n......\n").unwrap();
+    assert_eq!((first.width, first.height), (8, 4));
+    assert_eq!(second.start_body(), vec![(5, 1), (6, 1)]);
+    assert!(Level::parse("@@...\n").is_err());
+    assert!(Level::parse("length: 1\n#@...\n").is_ok());
+    assert!(Level::parse("length: 2\n#@...\n").is_err());
+    // the same rules as the editor, no food on the snake
+    assert!(Level::parse("length: 2\n.o@..\n").is_err());
+    assert!(Level::parse("length: 2\n.@o..\n").is_ok());
+
+    let levels = Arc::new(vec![first, second]);
+    let mut game = Game::with_levels(GameConfig::new(0, 0), levels, rng_from_seed(3));
+    game.step(None);
+    let events = game.step(None);
+    assert!(events.contains(&Event::LevelUp { level: 2 }));
+    assert_eq!(game.snake(0).head_position(), (5, 1));
+
+    game.step(None);
+    game.step(None);
+    let events = game.step(None);
+    assert!(game.is_wall(2, 1));
+    assert_eq!(events, vec![Event::Died { player: 0 }]);
+}
+
+#[test]
+fn versus_rounds() {
+    let mut game = Game::with_config(GameConfig::versus(40, 30), 5);
+    assert_eq!(game.snake(1).head_position(), (35, 27));
+    assert_eq!(game.snake(1).head_direction(), Direction::Left);
+
+    // player 2 turns up into the wall while player 1 keeps going
+    let mut events = Vec::new();
+    while !game.is_game_over() {
+        events = game.step_all(&[None, Some(Direction::Down)]);
+    }
+    assert!(events.contains(&Event::Died { player: 1 }));
+    assert!(events.contains(&Event::RoundOver { winner: Some(0) }));
+    assert_eq!(game.wins(), &[1, 0]);
+
+    for _ in 0..2 {
+        game.restart();
+        while !game.is_game_over() {
+            events = game.step_all(&[None, Some(Direction::Down)]);
+        }
+    }
+    assert!(events.contains(&Event::MatchOver { winner: 0 }));
+    assert!(game.is_match_over());
+
+    game.restart();
+    assert_eq!(game.wins(), &[0, 0]);
+}
+
+#[test]
+fn second_snake_must_fit() {
+    // player 2 starts turned half way around the board, into the wall
+    let level = Level::parse("length: 2\n......\n.@..#.\n......\n").unwrap();
+    assert!(level.check().is_ok());
+    assert!(level.check_spawns(1).is_ok());
+    assert!(level.check_spawns(2).is_err());
+    // or onto the first one
+    let level = Level::parse("length: 3\n.....\n..@..\n.....\n").unwrap();
+    assert!(level.check_spawns(2).is_err());
+    // or on the food
+    let level = Level::parse("length: 1\n..o\n...\n@..\n").unwrap();
+    assert!(level.check_spawns(2).is_err());
+    let level = Level::parse("length: 1\n...\n...\n@..\n").unwrap();
+    assert!(level.check_spawns(2).is_ok());
+}
+
+#[test]
+fn head_on_collision_is_a_draw() {
+    let level = Level::parse("length: 1\n@....\n").unwrap();
+    let levels = Arc::new(vec![level]);
+    let mut game = Game::with_levels(GameConfig::versus(0, 0), levels, rng_from_seed(1));
+    // player 1 at (1, 1) heading right, player 2 at (5, 1) heading left
+    game.step_all(&[None, None]);
+    let events = game.step_all(&[None, None]);
+    assert!(events.contains(&Event::Died { player: 0 }));
+    assert!(events.contains(&Event::Died { player: 1 }));
+    assert!(events.contains(&Event::RoundOver { winner: None }));
+    assert_eq!(game.wins(), &[0, 0]);
+}
+
+#[test]
+fn dying_snakes_block_the_way() {
+    let level = Level::parse("length: 2\no.....\n.@....\n").unwrap();
+    let levels = Arc::new(vec![level]);
+    let mut game = Game::with_levels(GameConfig::versus(0, 0), levels, rng_from_seed(1));
+    // player 1 heads right along the bottom row, player 2 left along the top
+    game.step_all(&[None, None]);
+    game.step_all(&[None, None]);
+    // player 2 runs into the border, so its tail stays where player 1 turns
+    let events = game.step_all(&[Some(Direction::Up), Some(Direction::Up)]);
+    assert!(events.contains(&Event::Died { player: 0 }));
+    assert!(events.contains(&Event::Died { player: 1 }));
+    assert!(events.contains(&Event::RoundOver { winner: None }));
+}
+
+#[test]
+fn arena_snakes_must_fit() {
+    assert!(Level::open(48, 36).check_spawns(8).is_ok());
+    // fewer rows than snakes puts two of them on the same row
+    let short = Level::open_with_length(10, 6, 5).unwrap();
+    assert!(short.check_spawns(3).is_ok());
+    assert!(short.check_spawns(8).is_err());
+    // a wall across the rows
+    let mut walled = Level::open(30, 20);
+    for y in 1..19 {
+        walled.walls.push((27, y));
+    }
+    assert!(walled.check_spawns(1).is_ok());
+    assert!(walled.check_spawns(4).is_err());
+}
+
+#[test]
+fn arena_last_snake_standing() {
+    for seed in 0..5 {
+        let mut game = Game::with_config(GameConfig::arena(30, 20, 8), seed);
+        let mut greedy = Greedy::new();
+        let mut remains = 0;
+        let mut match_over = None;
+
+        for _ in 0..5000 {
+            if game.is_game_over() {
+                break;
+            }
+            let dirs: Vec<Option<Direction>> = (0..game.players())
+                .map(|p| greedy.next_direction(&game, p))
+                .collect();
+            for event in game.step_all(&dirs) {
+                match event {
+                    Event::FoodSpawned { kind: FoodKind::Remains, .. } => remains += 1,
+                    Event::MatchOver { winner } => match_over = Some(winner),
+                    _ => {}
+                }
+            }
+
+            // dead snakes are off the board, only the living take up cells
+            let snakes: usize = (0..game.players())
+                .filter(|&p| game.is_alive(p))
+                .map(|p| game.snake(p).len())
+                .sum();
+            let interior = ((game.width() - 2) * (game.height() - 2)) as usize;
+            assert!(game.free_cells().len() + snakes + game.foods().len() >= interior);
+            for &(x, y) in game.free_cells() {
+                assert!(game.is_free(x, y));
+            }
+        }
+
+        assert!(game.is_game_over());
+        assert!(remains > 0);
+        let alive: Vec<usize> = (0..game.players()).filter(|&p| game.is_alive(p)).collect();
+        assert!(alive.len() <= 1);
+        assert_eq!(match_over, alive.first().cloned());
+    }
+}
+
+/// Lets `controller` play alone until the snake is `goal` long, returning
+/// the final length.
+fn autopilot<C: Controller>(game: &mut Game, controller: &mut C, goal: usize) -> usize {
+    for _ in 0..100_000 {
+        if game.is_game_over() || game.snake(0).len() >= goal {
+            break;
+        }
+        let dir = controller.next_direction(game, 0);
+        if let Some(d) = dir {
+            assert!(d != game.snake(0).head_direction().opposite());
+        }
+        game.step(dir);
+    }
+    game.snake(0).len()
+}
+
+#[test]
+fn greedy_finds_food() {
+    for seed in 0..10 {
+        let mut game = Game::new(20, 16, seed);
+        assert_eq!(autopilot(&mut game, &mut Greedy::new(), 20), 20);
+    }
+}
+
+#[test]
+fn hamiltonian_fills_the_board() {
+    for seed in 0..10 {
+        // 8x6 cells inside the border
+        let mut game = Game::new(10, 8, seed);
+        let mut events = Vec::new();
+        let mut controller = Hamiltonian::new();
+        while !game.is_game_over() {
+            let dir = controller.next_direction(&game, 0);
+            events = game.step(dir);
+        }
+        assert!(game.is_won());
+        assert_eq!(events.last(), Some(&Event::BoardFilled));
+        assert_eq!(game.snake(0).len(), 48);
+        assert!(game.free_cells().is_empty());
+    }
+}
+
+#[test]
+fn quick_double_turn() {
+    let mut game = Game::new(40, 30, 7);
+    let mut input = InputQueue::new();
+
+    // Down then Left within one tick, while heading right
+    assert!(input.push(Direction::Down, game.snake(0).head_direction()));
+    assert!(input.push(Direction::Left, game.snake(0).head_direction()));
+    assert!(!input.push(Direction::Right, game.snake(0).head_direction()));
+
+    game.step(input.pop());
+    assert_eq!(game.snake(0).head_position(), (4, 3));
+    game.step(input.pop());
+    assert_eq!(game.snake(0).head_position(), (3, 3));
+    assert!(!game.is_game_over());
+    assert_eq!(input.pop(), None);
+}
+
+#[test]
+fn special_foods_come_and_go() {
+    let mut game = Game::new(40, 30, 3);
+    let mut greedy = Greedy::new();
+    let mut seen = Vec::new();
+    let mut expired = 0;
+
+    for _ in 0..3000 {
+        if game.is_game_over() {
+            game.restart();
+        }
+        let dir = greedy.next_direction(&game, 0);
+        for event in game.step(dir) {
+            match event {
+                Event::FoodSpawned { kind, .. } if !seen.contains(&kind) => seen.push(kind),
+                Event::FoodExpired { .. } => expired += 1,
+                _ => {}
+            }
+        }
+        let apples = game.foods().iter().filter(|f| f.kind == FoodKind::Apple).count();
+        assert!(game.is_game_over() || apples == 1);
+        assert!(game.foods().len() <= 3);
+
+        let snakes = game.snake(0).len();
+        let interior = ((game.width() - 2) * (game.height() - 2)) as usize;
+        if !game.is_game_over() {
+            assert_eq!(game.free_cells().len(), interior - snakes - game.foods().len());
+        }
+        for &(x, y) in game.free_cells() {
+            assert!(game.is_free(x, y) && game.foods().iter().all(|f| (f.x, f.y) != (x, y)));
+        }
+    }
+    assert_eq!(seen.len(), 6);
+    assert!(expired > 0);
+    assert!(game.effects(0).iter().all(|e| e.steps_left <= e.effect.duration()));
+}
+
+#[test]
+fn replays_play_back_the_same_game() {
+    let levels = level::load_sequence(Path::new("resources/levels")).unwrap();
+    for &(seed, ref levels) in &[(11, Vec::new()), (12, levels)] {
+        let mut recording = Replay::new(GameConfig::versus(40, 30), levels.clone(), seed);
+        let mut game = recording.game();
+        let mut keys = rng_from_seed(seed);
+        let mut log = Vec::new();
+
+        for _ in 0..3000 {
+            if game.is_game_over() {
+                game.restart();
+                recording.record_restart();
+                continue;
+            }
+            let dirs = [*keys.choose(&DIRECTIONS).unwrap(), *keys.choose(&DIRECTIONS).unwrap()];
+            log.push(game.step_all(&dirs));
+            recording.record_step(&dirs);
+        }
+        assert!(recording.moves.contains(&Move::Restart));
+
+        let replay = Replay::from_bytes(&recording.to_bytes()).unwrap();
+        assert_eq!(replay, recording);
+
+        let mut game = replay.game();
+        let mut playback = Playback::new(replay);
+        let mut played = Vec::new();
+        while let Some(events) = playback.step(&mut game) {
+            // restarts have no events
+            if !events.is_empty() {
+                played.push(events);
+            }
+        }
+        assert_eq!(played, log);
+    }
+    assert!(Replay::from_bytes(b"SNKR").is_err());
+}
+
+#[test]
+fn daily_challenge() {
+    assert_eq!(Date::from_days(0).to_string(), "1970-01-01");
+    assert_eq!(Date::from_days(19_782), Date::parse("2024-02-29").unwrap());
+    assert!(Date::parse("2024-13-01").is_none());
+
+    let date = Date::parse("2026-10-18").unwrap();
+    let challenge = Challenge::new(date);
+    assert_eq!(challenge, Challenge::new(date));
+    assert_eq!(challenge.level(), Challenge::new(date).level());
+    assert!(challenge.level().check().is_ok());
+    let next_day = Challenge::new(Date::from_days(20_745));
+    assert_ne!(challenge.seed, next_day.seed);
+    assert_ne!(challenge.level().walls, next_day.level().walls);
+
+    let mut recording = challenge.replay();
+    let mut game = recording.game();
+    let mut greedy = Greedy::new();
+    while !game.is_game_over() {
+        let dirs = [greedy.next_direction(&game, 0)];
+        recording.record_step(&dirs);
+        game.step_all(&dirs);
+    }
+    assert!(game.steps() <= daily::STEPS);
+
+    let replay = Replay::from_bytes(&recording.to_bytes()).unwrap();
+    assert_eq!(daily::verify(&replay), Ok((date, game.score(0))));
+    assert!(next_day.verify(&replay).is_err());
+    let mut cut = replay.clone();
+    cut.moves.pop();
+    assert!(challenge.verify(&cut).is_err());
+
+    let result = DailyResult {
+        date,
+        seed: challenge.seed,
+        score: game.score(0),
+        length: game.snake(0).len(),
+        replay: String::from("daily-2026-10-18-1.replay"),
+    };
+    assert_eq!(DailyResult::parse(&result.to_line()), Some(result));
+}
+
+#[test]
+fn levels_round_trip_as_text() {
+    for level in level::load_sequence(Path::new("resources/levels")).unwrap() {
+        assert_eq!(Level::parse(&level.to_text()).unwrap(), level);
+    }
+}
+
+#[test]
+fn editing_a_level() {
+    let mut level = Level::open(12, 8);
+    for x in 3..9 {
+        level.set_cell(x, 5, Cell::Wall);
+    }
+    level.set_cell(4, 5, Cell::Floor);
+    level.set_cell(8, 5, Cell::Food);
+    level.set_cell(9, 6, Cell::FoodZone);
+    // the border isn't part of the map
+    level.set_cell(0, 3, Cell::Wall);
+    assert_eq!(level.cell(3, 5), Cell::Wall);
+    assert_eq!(level.cell(4, 5), Cell::Floor);
+    assert_eq!(level.cell(8, 5), Cell::Food);
+    assert!(!level.is_wall(0, 3));
+
+    // the snake doesn't fit into a wall, or with its tail off the map
+    assert!(level.set_start((6, 5), Direction::Right).is_err());
+    assert!(level.set_start((1, 3), Direction::Right).is_err());
+    assert_eq!((level.start, level.direction), ((4, 2), Direction::Right));
+    level.set_start((3, 3), Direction::Down).unwrap();
+    assert_eq!(level.start_body(), vec![(3, 3), (3, 2), (3, 1)]);
+    level.set_cell(3, 2, Cell::Food);
+    assert!(level.check().is_err());
+    level.set_cell(3, 2, Cell::Floor);
+    assert!(level.check().is_ok());
+
+    let saved = Level::parse(&level.to_text()).unwrap();
+    assert_eq!((saved.start, saved.direction), (level.start, level.direction));
+    for y in 0..level.height {
+        for x in 0..level.width {
+            assert_eq!(saved.cell(x, y), level.cell(x, y));
+        }
+    }
+}
+
+#[test]
+fn themes_from_files() {
+    let themes = theme::load_themes(Path::new("resources/themes")).unwrap();
+    let names: Vec<&str> = themes.iter().map(|theme| theme.name.as_str()).collect();
+    assert_eq!(names, ["classic", "dark", "high-contrast", "nokia"]);
+    assert!(themes[0].sprites.head.is_none());
+    for sprite in &[&themes[3].sprites.head, &themes[3].sprites.food] {
+        assert!(sprite.as_ref().unwrap().exists());
+    }
+
+    let theme = Theme::parse("name: mine\nbackground: #ff000080\nplayers: #000000 #ffffff\n").unwrap();
+    assert_eq!(theme.name, "mine");
+    assert_eq!(theme.background, [1.0, 0.0, 0.0, 128.0 / 255.0]);
+    assert_eq!(theme.players[2], [0.0, 0.0, 0.0, 1.0]);
+    assert_eq!(theme.players[3], [1.0, 1.0, 1.0, 1.0]);
+    assert_eq!(theme.apple, Theme::classic().apple);
+    assert!(Theme::parse("background: red\n").is_err());
+    assert!(Theme::parse("background: #12345\n").is_err());
+    assert!(Theme::parse("colour: #123456\n").is_err());
+}
+
+fn args(line: &str) -> Vec<String> {
+    line.split_whitespace().map(String::from).collect()
+}
+
+#[test]
+fn options_from_config_and_flags() {
+    let options = Options::default()
+        .apply_config("; mine\nwidth: 60\nspeed: 8\nwrap: true\ntheme: dark\n")
+        .unwrap()
+        .apply_args(args("--speed 12 --length 6 --versus"))
+        .unwrap();
+    assert_eq!(options.board_size(), (60, 30));
+    assert!(!options.uses_levels());
+    assert_eq!(options.speed, 12.0);
+    assert_eq!(options.length, Some(6));
+    assert!(options.wrap && options.versus);
+    assert_eq!(options.theme, "dark");
+    assert!(options.validate().is_ok());
+
+    assert!(Options::parse_args(args("--width")).is_err());
+    assert!(Options::parse_args(args("--width ten")).is_err());
+    assert!(Options::parse_args(args("--fast")).is_err());
+    assert!(Options::default().apply_config("colour: red\n").is_err());
+    let impossible = [
+        "--width 5",
+        "--height 1001",
+        "--cell-size 0",
+        "--speed 0",
+        "--speed NaN",
+        "--length 0",
+        "--width 20 --length 19",
+        "--daily --versus",
+    ];
+    for bad in &impossible {
+        let options = Options::parse_args(args(bad)).unwrap();
+        assert!(options.validate().is_err(), "{} should not be valid", bad);
+    }
+
+    let level = Level::open_with_length(20, 10, 18).unwrap();
+    assert_eq!(level.start_body().last(), Some(&(1, 2)));
+    let game = Game::with_levels(GameConfig::new(0, 0), Arc::new(vec![level]), rng_from_seed(1));
+    assert_eq!(game.snake(0).len(), 18);
+    let level = Level::parse("length: 2\n..@...\n").unwrap();
+    assert!(level.clone().with_length(3).is_ok());
+    assert!(level.with_length(4).is_err());
+
+    // on a small board the first apple goes anywhere free instead
+    assert!(Level::open_with_length(5, 5, 3).is_err());
+    let small = Level::open(7, 5);
+    assert!(small.food.is_empty() && small.check().is_ok());
+    let game = Game::with_levels(GameConfig::new(0, 0), Arc::new(vec![small]), rng_from_seed(1));
+    assert!(game.foods().iter().all(|food| game.bounds().contains(food.x, food.y)));
+    assert_eq!(game.foods().len(), 1);
+}
+
+#[test]
+fn env_episodes() {
+    let mut config = EnvConfig::new(12, 10);
+    config.rewards.closer = 0.1;
+    let mut env = Env::new(config);
+    assert_eq!(env.reset(5), env.reset(5));
+    assert_eq!(env.observe().len(), FEATURES);
+
+    // head at (4, 2) heading right, the apple at (6, 4)
+    let (_, reward, done) = env.step(Action::Straight);
+    assert_eq!((reward, done), (0.1, false));
+    env.step(Action::Straight);
+    env.step(Action::Right);
+    let (_, reward, _) = env.step(Action::Straight);
+    assert_eq!(reward, 1.0);
+
+    // going round in a circle ends the episode once the snake starves
+    let mut steps = 0;
+    while !env.step(Action::Left).2 {
+        steps += 1;
+    }
+    assert!(!env.game().is_game_over());
+    assert_eq!(steps + 1, 120);
+    assert_eq!(env.step(Action::Straight).1, 0.0);
+
+    env.reset(5);
+    let mut last = (Vec::new(), 0.0, false);
+    while !last.2 {
+        last = env.step(Action::Straight);
+    }
+    assert!(env.game().is_game_over());
+    assert_eq!(last.1, -1.0);
+
+    config.observation = Observation::Grid;
+    let mut env = Env::new(config);
+    let grid = env.reset(1);
+    assert_eq!(env.observation_shape(), vec![GRID_CHANNELS, 10, 12]);
+    assert_eq!(grid.len(), GRID_CHANNELS * 120);
+    // head, then the apple
+    assert_eq!(grid[2 * 120 + 2 * 12 + 4], 1.0);
+    assert_eq!(grid[3 * 120 + 4 * 12 + 6], 1.0);
+    assert_eq!(grid[3 * 120..].iter().sum::<f32>(), 1.0);
+}
+
+#[test]
+#[should_panic(expected = "the board must be at least 10x10, not 10x5")]
+fn env_board_too_small() {
+    Env::new(EnvConfig::new(10, 5));
+}
+
+#[test]
+fn vec_env_matches_single_envs() {
+    // a small batch steps on this thread, a big one over several
+    for &count in &[6, 64] {
+        check_vec_env(count);
+    }
+}
+
+fn check_vec_env(count: usize) {
+    let config = EnvConfig::new(10, 10);
+    let mut batch = VecEnv::new(config, count);
+    let mut singles: Vec<Env> = (0..count).map(|_| Env::new(config)).collect();
+    let mut seeds: Vec<u64> = (0..count as u64).map(|i| 100 + i).collect();
+
+    let observations = batch.reset(100);
+    for (i, env) in singles.iter_mut().enumerate() {
+        assert_eq!(env.reset(seeds[i]), observations[i]);
+        seeds[i] += count as u64;
+    }
+
+    let mut keys = rng_from_seed(9);
+    for _ in 0..2000 {
+        let actions: Vec<Action> = (0..count)
+            .map(|_| Action::from_index(keys.gen_range(0, 3)).unwrap())
+            .collect();
+        let results = batch.step(&actions);
+        for (i, env) in singles.iter_mut().enumerate() {
+            let (mut observation, reward, done) = env.step(actions[i]);
+            if done {
+                observation = env.reset(seeds[i]);
+                seeds[i] += count as u64;
+            }
+            assert_eq!(results[i], (observation, reward, done));
+        }
+    }
+}
+
+#[test]
+fn empty_vec_env() {
+    let mut batch = VecEnv::new(EnvConfig::new(10, 10), 0);
+    assert!(batch.is_empty());
+    assert!(batch.reset(1).is_empty());
+    assert!(batch.step(&[]).is_empty());
+}
+
+#[test]
+fn rewind_takes_back_steps() {
+    let mut game = Game::new(20, 16, 5);
+    let mut greedy = Greedy::new();
+    let mut rewind = Rewind::new(30);
+    let mut history = Vec::new();
+
+    for _ in 0..100 {
+        let blocks: Vec<(i32, i32)> = game.snake(0).blocks().map(|b| (b.x, b.y)).collect();
+        history.push((blocks, game.snake(0).head_direction(), game.food(), game.score(0)));
+        rewind.record(&game);
+        let dir = greedy.next_direction(&game, 0);
+        game.step(dir);
+    }
+    assert_eq!(rewind.len(), rewind.capacity());
+
+    // only the last 30 steps can be taken back, newest first
+    for expected in history.iter().rev().take(30) {
+        assert!(rewind.step_back(&mut game));
+        let blocks: Vec<(i32, i32)> = game.snake(0).blocks().map(|b| (b.x, b.y)).collect();
+        assert_eq!(&(blocks, game.snake(0).head_direction(), game.food(), game.score(0)), expected);
+    }
+    assert!(!rewind.step_back(&mut game));
+
+    // and play goes on from there on a consistent board
+    assert_eq!(autopilot(&mut game, &mut greedy, 20), 20);
+    let interior = ((game.width() - 2) * (game.height() - 2)) as usize;
+    assert_eq!(game.free_cells().len(), interior - game.snake(0).len() - game.foods().len());
+}
+
+#[test]
+fn rewind_and_replay_is_the_same_game() {
+    let mut game = Game::new(20, 16, 8);
+    let mut greedy = Greedy::new();
+    let mut rewind = Rewind::new(60);
+    let mut dirs = Vec::new();
+    for _ in 0..200 {
+        rewind.record(&game);
+        let dir = greedy.next_direction(&game, 0);
+        dirs.push(dir);
+        game.step(dir);
+    }
+    let state = |game: &Game| {
+        let blocks: Vec<(i32, i32)> = game.snake(0).blocks().map(|b| (b.x, b.y)).collect();
+        (blocks, game.foods().to_vec(), game.score(0))
+    };
+    let end = state(&game);
+
+    for _ in 0..60 {
+        assert!(rewind.step_back(&mut game));
+    }
+    // food eaten since then has to come back in the same places
+    assert!(game.score(0) < end.2);
+    for &dir in &dirs[140..] {
+        game.step(dir);
+    }
+    assert_eq!(state(&game), end);
+}